@@ -0,0 +1,188 @@
+use crate::std::{
+    ops::{Add, Mul},
+    vec::Vec,
+};
+
+use num_traits::Num;
+
+use crate::{Event, SignalTrans, SF};
+
+// Behavior output which may or may not have a command. Converting with Into
+// would be ambiguous since every type converts into an Option of itself.
+pub trait MaybeCommand {
+    type Command;
+
+    fn command(self) -> Option<Self::Command>;
+}
+
+impl<T> MaybeCommand for Event<T> {
+    type Command = T;
+
+    fn command(self) -> Option<T> {
+        self.into()
+    }
+}
+
+impl<T> MaybeCommand for Option<T> {
+    type Command = T;
+
+    fn command(self) -> Option<T> {
+        self
+    }
+}
+
+type CommandOf<Time, S> =
+    <<S as SignalTrans<Time>>::Output as MaybeCommand>::Command;
+
+// Output of an arbiter, along with which behaviors produced it (in order of
+// priority) for diagnostics
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arbitration<Cmd> {
+    pub command: Option<Cmd>,
+    pub active: Vec<usize>,
+}
+
+impl<Cmd> Arbitration<Cmd> {
+    // The highest priority active behavior
+    pub fn winner(&self) -> Option<usize> {
+        self.active.first().copied()
+    }
+}
+
+//
+// Priority
+//
+
+// Runs all behaviors, outputting the command of the first one which has one,
+// so earlier behaviors subsume later ones
+#[derive(Clone)]
+pub struct Priority<S> {
+    behaviors: Vec<S>,
+}
+
+impl<Time, S> SignalTrans<Time> for Priority<S>
+where
+    Time: Copy,
+    S: SignalTrans<Time>,
+    S::Input: Clone,
+    S::Output: MaybeCommand,
+{
+    type Input = S::Input;
+    type Output = Arbitration<CommandOf<Time, S>>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let mut output = Arbitration {
+            command: None,
+            active: Vec::new(),
+        };
+
+        // Every behavior is stepped so that they all keep up with time
+        let behaviors = self
+            .behaviors
+            .into_iter()
+            .enumerate()
+            .map(|(i, behavior)| {
+                let (behavior, command) = behavior.step(delta, input.clone());
+                if let Some(command) = command.command() {
+                    output.active.push(i);
+                    output.command.get_or_insert(command);
+                }
+                behavior
+            })
+            .collect();
+
+        (Priority { behaviors }, output)
+    }
+}
+
+// Highest priority first. Use boxed() to mix different types of behaviors.
+pub fn priority<Time, S>(behaviors: Vec<SF<Time, S>>) -> SF<Time, Priority<S>>
+where
+    Time: Copy,
+    S: SignalTrans<Time>,
+    S::Input: Clone,
+    S::Output: MaybeCommand,
+{
+    SF::from(Priority {
+        behaviors: behaviors.into_iter().map(SF::into_inner).collect(),
+    })
+}
+
+//
+// Blend
+//
+
+// Runs all behaviors, outputting the weighted average of the commands of those
+// which have one
+#[derive(Clone)]
+pub struct Blend<S, W> {
+    behaviors: Vec<(W, S)>,
+}
+
+impl<Time, S, W> SignalTrans<Time> for Blend<S, W>
+where
+    Time: Copy,
+    S: SignalTrans<Time>,
+    S::Input: Clone,
+    S::Output: MaybeCommand,
+    W: Num + Copy,
+    CommandOf<Time, S>:
+        Add<Output = CommandOf<Time, S>> + Mul<W, Output = CommandOf<Time, S>>,
+{
+    type Input = S::Input;
+    type Output = Arbitration<CommandOf<Time, S>>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let mut active = Vec::new();
+        let mut commands = Vec::new();
+        let mut total = W::zero();
+
+        let behaviors = self
+            .behaviors
+            .into_iter()
+            .enumerate()
+            .map(|(i, (weight, behavior))| {
+                let (behavior, command) = behavior.step(delta, input.clone());
+                if let Some(command) = command.command() {
+                    active.push(i);
+                    commands.push((weight, command));
+                    total = total + weight;
+                }
+                (weight, behavior)
+            })
+            .collect();
+
+        let command = if total.is_zero() {
+            None
+        } else {
+            commands
+                .into_iter()
+                .map(|(weight, command)| command * (weight / total))
+                .reduce(|sum, command| sum + command)
+        };
+
+        (Blend { behaviors }, Arbitration { command, active })
+    }
+}
+
+// Behaviors with no command (or a total weight of 0) are left out of the
+// average
+pub fn blend<Time, S, W>(
+    behaviors: Vec<(W, SF<Time, S>)>,
+) -> SF<Time, Blend<S, W>>
+where
+    Time: Copy,
+    S: SignalTrans<Time>,
+    S::Input: Clone,
+    S::Output: MaybeCommand,
+    W: Num + Copy,
+    CommandOf<Time, S>:
+        Add<Output = CommandOf<Time, S>> + Mul<W, Output = CommandOf<Time, S>>,
+{
+    SF::from(Blend {
+        behaviors: behaviors
+            .into_iter()
+            .map(|(weight, behavior)| (weight, behavior.into_inner()))
+            .collect(),
+    })
+}
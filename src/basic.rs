@@ -88,9 +88,15 @@ pub struct LocalTime<Time> {
 // Lift
 //
 
-#[derive(Clone)]
 pub struct Lift<Time, A, B>(Rc<dyn Fn(Time, A) -> B>);
 
+// Derive would require Time, A and B to be Clone
+impl<Time, A, B> Clone for Lift<Time, A, B> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
 impl<Time, A, B, F> From<F> for Lift<Time, A, B>
 where
     F: Fn(Time, A) -> B + 'static,
@@ -179,6 +185,10 @@ where
 
         (Compose::new(t_next, u_next), c)
     }
+
+    fn leftover(&self) -> Option<Time> {
+        self.right.leftover().or_else(|| self.left.leftover())
+    }
 }
 
 pub fn compose<Time, T, U>(
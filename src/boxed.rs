@@ -0,0 +1,86 @@
+use crate::std::boxed::Box;
+
+use crate::{SignalTrans, SF};
+
+// Object-safe version of SignalTrans, so that SFs of different types (but the
+// same input and output) can be stored and swapped at runtime
+trait DynSignalTrans<Time, A, B> {
+    fn step_box(
+        self: Box<Self>,
+        delta: Time,
+        input: A,
+    ) -> (Box<dyn DynSignalTrans<Time, A, B>>, B);
+
+    fn leftover(&self) -> Option<Time>;
+
+    fn clone_box(&self) -> Box<dyn DynSignalTrans<Time, A, B>>;
+}
+
+impl<Time, S> DynSignalTrans<Time, S::Input, S::Output> for S
+where
+    S: SignalTrans<Time> + Clone + 'static,
+{
+    fn step_box(
+        self: Box<Self>,
+        delta: Time,
+        input: S::Input,
+    ) -> (
+        Box<dyn DynSignalTrans<Time, S::Input, S::Output>>,
+        S::Output,
+    ) {
+        let (next, output) = (*self).step(delta, input);
+        (Box::new(next), output)
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        SignalTrans::leftover(self)
+    }
+
+    fn clone_box(&self) -> Box<dyn DynSignalTrans<Time, S::Input, S::Output>> {
+        Box::new(self.clone())
+    }
+}
+
+//
+// BoxedSF
+//
+
+pub struct BoxedSF<Time, A, B>(Box<dyn DynSignalTrans<Time, A, B>>);
+
+impl<Time, A, B> BoxedSF<Time, A, B> {
+    pub fn new<S>(sf: S) -> Self
+    where
+        S: SignalTrans<Time, Input = A, Output = B> + Clone + 'static,
+    {
+        BoxedSF(Box::new(sf))
+    }
+}
+
+impl<Time, A, B> Clone for BoxedSF<Time, A, B> {
+    fn clone(&self) -> Self {
+        BoxedSF(self.0.clone_box())
+    }
+}
+
+impl<Time, A, B> SignalTrans<Time> for BoxedSF<Time, A, B> {
+    type Input = A;
+    type Output = B;
+
+    fn step(self, delta: Time, input: A) -> (Self, B) {
+        let (next, output) = self.0.step_box(delta, input);
+        (BoxedSF(next), output)
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        self.0.leftover()
+    }
+}
+
+pub fn boxed<Time, S>(
+    sf: SF<Time, S>,
+) -> SF<Time, BoxedSF<Time, S::Input, S::Output>>
+where
+    S: SignalTrans<Time> + Clone + 'static,
+{
+    SF::from(BoxedSF::new(sf.into_inner()))
+}
@@ -0,0 +1,695 @@
+// Behavior trees, where each node is a task which stops with Success or
+// Failure. Composites are "with memory": children which have already stopped
+// are not run again, so actions don't restart each step.
+
+use crate::std::{marker::PhantomData, ops::Sub, rc::Rc, vec::Vec};
+
+use num_traits::Num;
+
+use crate::{
+    task, BasicTask, MapOutput, Occurrences, RetryPolicy, SFExt, SignalTrans,
+    TaskOutput, TaskState, SF,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Status {
+    Success,
+    Failure,
+}
+
+impl Status {
+    pub fn is_success(&self) -> bool {
+        *self == Status::Success
+    }
+
+    pub fn is_failure(&self) -> bool {
+        *self == Status::Failure
+    }
+
+    pub fn invert(self) -> Self {
+        match self {
+            Status::Success => Status::Failure,
+            Status::Failure => Status::Success,
+        }
+    }
+}
+
+impl From<bool> for Status {
+    fn from(success: bool) -> Self {
+        if success {
+            Status::Success
+        } else {
+            Status::Failure
+        }
+    }
+}
+
+//
+// Leaves
+//
+
+// Stops on the first step with whether the predicate holds for the input,
+// without taking any time
+#[derive(Clone)]
+pub enum Condition<Time, A, Out> {
+    Running(Rc<dyn Fn(&A) -> bool>, PhantomData<Out>),
+    Stopped(Status, Time),
+}
+
+impl<Time, A, Out> SignalTrans<Time> for Condition<Time, A, Out>
+where
+    Time: Copy,
+{
+    type Input = A;
+    type Output = TaskOutput<Out, Status>;
+
+    fn step(self, delta: Time, input: A) -> (Self, Self::Output) {
+        let status = match self {
+            Condition::Running(pred, _) => Status::from(pred(&input)),
+            Condition::Stopped(status, _) => status,
+        };
+        (
+            Condition::Stopped(status, delta),
+            TaskOutput::Stopped(status),
+        )
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        match self {
+            Condition::Running(..) => None,
+            Condition::Stopped(_, leftover) => Some(*leftover),
+        }
+    }
+}
+
+pub fn condition<Time, A, Out, F>(pred: F) -> SF<Time, Condition<Time, A, Out>>
+where
+    Time: Copy,
+    F: Fn(&A) -> bool + 'static,
+{
+    SF::from(Condition::Running(Rc::new(pred), PhantomData))
+}
+
+// Runs run until stop has a Status event
+pub fn action<Time, S, T>(
+    run: SF<Time, S>,
+    stop: SF<Time, T>,
+) -> SF<Time, BasicTask<Time, Status, S, T>>
+where
+    Time: Copy,
+    S: SignalTrans<Time>,
+    T: SignalTrans<Time, Input = S::Input>,
+    T::Output: Occurrences<Time, Value = Status>,
+    S::Input: Clone,
+{
+    task(run, stop)
+}
+
+//
+// Sequence and Fallback
+//
+
+// Runs children one at a time until one stops with the given status, or all
+// of them have stopped. Later children are run for the rest of the step when
+// one stops.
+#[derive(Clone)]
+pub enum Composite<Time, S> {
+    Running {
+        current: S,
+        // Children after the current one, in reverse order
+        rest: Vec<S>,
+        index: usize,
+        until: Status,
+    },
+    Stopped(Status, Option<Time>),
+}
+
+impl<Time, S> Composite<Time, S> {
+    fn new(mut children: Vec<S>, until: Status) -> Self {
+        children.reverse();
+        match children.pop() {
+            Some(current) => Composite::Running {
+                current,
+                rest: children,
+                index: 0,
+                until,
+            },
+            None => Composite::Stopped(until.invert(), None),
+        }
+    }
+
+    // Index of the running child
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            Composite::Running { index, .. } => Some(*index),
+            Composite::Stopped(..) => None,
+        }
+    }
+}
+
+impl<Time, Out, S> SignalTrans<Time> for Composite<Time, S>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, Status>>,
+    S::Input: Clone,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<Out, Status>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let (mut current, mut rest, mut index, until) = match self {
+            Composite::Running {
+                current,
+                rest,
+                index,
+                until,
+            } => (current, rest, index, until),
+            Composite::Stopped(status, _) => {
+                return (
+                    Composite::Stopped(status, Some(delta)),
+                    TaskOutput::Stopped(status),
+                );
+            }
+        };
+
+        let mut time = delta;
+        loop {
+            let (current_next, val) = current.step(time, input.clone());
+            let status = match val {
+                TaskOutput::Running(val) => {
+                    return (
+                        Composite::Running {
+                            current: current_next,
+                            rest,
+                            index,
+                            until,
+                        },
+                        TaskOutput::Running(val),
+                    );
+                }
+                TaskOutput::Stopped(status) => status,
+            };
+
+            let leftover = current_next.leftover();
+            match rest.pop() {
+                Some(next) if status != until => {
+                    current = next;
+                    index += 1;
+                    time = leftover.unwrap_or(time);
+                }
+                _ => {
+                    return (
+                        Composite::Stopped(status, leftover),
+                        TaskOutput::Stopped(status),
+                    );
+                }
+            }
+        }
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        match self {
+            Composite::Running { current, .. } => current.leftover(),
+            Composite::Stopped(_, leftover) => *leftover,
+        }
+    }
+}
+
+// Succeeds if all children succeed, stopping at the first failure. Use boxed()
+// to mix different types of nodes.
+pub fn sequence<Time, Out, S>(
+    children: Vec<SF<Time, S>>,
+) -> SF<Time, Composite<Time, S>>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, Status>>,
+    S::Input: Clone,
+{
+    SF::from(Composite::new(
+        children.into_iter().map(SF::into_inner).collect(),
+        Status::Failure,
+    ))
+}
+
+// Fails if all children fail, stopping at the first success. Also known as a
+// selector.
+pub fn fallback<Time, Out, S>(
+    children: Vec<SF<Time, S>>,
+) -> SF<Time, Composite<Time, S>>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, Status>>,
+    S::Input: Clone,
+{
+    SF::from(Composite::new(
+        children.into_iter().map(SF::into_inner).collect(),
+        Status::Success,
+    ))
+}
+
+//
+// Parallel
+//
+
+// Runs all children at the same time, succeeding once threshold of them have
+// succeeded or failing once too many have failed for that to happen. Children
+// still running at that point are dropped. While running, the outputs (None for
+// children which have stopped) are combined into one.
+#[derive(Clone)]
+pub enum Parallel<Time, S, Out, C> {
+    Running {
+        children: Vec<TaskState<Time, S, Status>>,
+        threshold: usize,
+        combine: Rc<dyn Fn(Vec<Option<Out>>) -> C>,
+    },
+    Stopped(Status, Option<Time>),
+}
+
+impl<Time, S, Out, C> SignalTrans<Time> for Parallel<Time, S, Out, C>
+where
+    Time: Copy + PartialOrd,
+    S: SignalTrans<Time, Output = TaskOutput<Out, Status>>,
+    S::Input: Clone,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<C, Status>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let (children, threshold, combine) = match self {
+            Parallel::Running {
+                children,
+                threshold,
+                combine,
+            } => (children, threshold, combine),
+            Parallel::Stopped(status, _) => {
+                return (
+                    Parallel::Stopped(status, Some(delta)),
+                    TaskOutput::Stopped(status),
+                );
+            }
+        };
+
+        let total = children.len();
+        let mut successes = 0;
+        let mut failures = 0;
+        let mut stopped = Vec::new();
+        let mut outputs = Vec::with_capacity(total);
+        let mut children_next = Vec::with_capacity(total);
+        for child in children {
+            if let Some(status) = child.end() {
+                if status.is_success() {
+                    successes += 1;
+                } else {
+                    failures += 1;
+                }
+            }
+
+            let was_running = child.is_running();
+            let (child_next, val) = child.step(delta, input.clone());
+            match val {
+                TaskOutput::Running(val) => outputs.push(Some(val)),
+                TaskOutput::Stopped(status) => {
+                    outputs.push(None);
+                    if was_running {
+                        stopped.push((child_next.leftover(), status));
+                    }
+                }
+            }
+            children_next.push(child_next);
+        }
+
+        // Count the children which stopped this step in the order they
+        // stopped, so the leftover is from the one which decided the result
+        stopped.sort_by(|(a, _), (b, _)| {
+            b.partial_cmp(a).unwrap_or(crate::std::cmp::Ordering::Equal)
+        });
+        for (leftover, status) in stopped {
+            if status.is_success() {
+                successes += 1;
+            } else {
+                failures += 1;
+            }
+
+            let result = if successes >= threshold {
+                Some(Status::Success)
+            } else if failures > total - threshold {
+                Some(Status::Failure)
+            } else {
+                None
+            };
+            if let Some(status) = result {
+                return (
+                    Parallel::Stopped(status, leftover),
+                    TaskOutput::Stopped(status),
+                );
+            }
+        }
+
+        let output = TaskOutput::Running(combine(outputs));
+        (
+            Parallel::Running {
+                children: children_next,
+                threshold,
+                combine,
+            },
+            output,
+        )
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        match self {
+            Parallel::Running { .. } => None,
+            Parallel::Stopped(_, leftover) => *leftover,
+        }
+    }
+}
+
+pub fn parallel<Time, S, Out, C, F>(
+    threshold: usize,
+    children: Vec<SF<Time, S>>,
+    combine: F,
+) -> SF<Time, Parallel<Time, S, Out, C>>
+where
+    Time: Copy + PartialOrd,
+    S: SignalTrans<Time, Output = TaskOutput<Out, Status>>,
+    S::Input: Clone,
+    F: Fn(Vec<Option<Out>>) -> C + 'static,
+{
+    assert!(
+        threshold > 0 && threshold <= children.len(),
+        "threshold must be between 1 and the number of children"
+    );
+
+    SF::from(Parallel::Running {
+        children: children
+            .into_iter()
+            .map(|child| TaskState::new(child.into_inner()))
+            .collect(),
+        threshold,
+        combine: Rc::new(combine),
+    })
+}
+
+//
+// Decorators
+//
+
+#[derive(Copy, Clone)]
+pub struct Inverter<S>(S);
+
+impl<Time, Out, S> SignalTrans<Time> for Inverter<S>
+where
+    S: SignalTrans<Time, Output = TaskOutput<Out, Status>>,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<Out, Status>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let (child, val) = self.0.step(delta, input);
+        (Inverter(child), val.map_stopped(Status::invert))
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        self.0.leftover()
+    }
+}
+
+pub fn inverter<Time, Out, S>(child: SF<Time, S>) -> SF<Time, Inverter<S>>
+where
+    S: SignalTrans<Time, Output = TaskOutput<Out, Status>>,
+{
+    SF::from(Inverter(child.into_inner()))
+}
+
+// Restarts a failed child from a clone of its initial state, failing once it
+// has failed max_attempts times. The output is None on steps which end with
+// the child waiting to restart.
+pub type Retry<Time, Out, S> = MapOutput<
+    Time,
+    crate::Retry<
+        Time,
+        MapOutput<Time, S, TaskOutput<Out, Result<(), ()>>>,
+        (),
+        (),
+    >,
+    TaskOutput<Option<Out>, Status>,
+>;
+
+pub fn retry<Time, Out, S>(
+    max_attempts: usize,
+    child: SF<Time, S>,
+) -> SF<Time, Retry<Time, Out, S>>
+where
+    Time: Copy + PartialOrd + Sub<Output = Time>,
+    S: SignalTrans<Time, Output = TaskOutput<Out, Status>> + Clone,
+    S::Input: Clone,
+{
+    let policy = RetryPolicy {
+        max_attempts,
+        backoff: None,
+    };
+    let child = child.map(|val: TaskOutput<Out, Status>| {
+        val.map_stopped(
+            |status| if status.is_success() { Ok(()) } else { Err(()) },
+        )
+    });
+    crate::retry(child, policy)
+        .map(|val| val.map_stopped(|end| Status::from(end.is_ok())))
+}
+
+// Fails if the child hasn't stopped within the time limit. The child wins if
+// it stops right at the limit.
+#[derive(Copy, Clone)]
+pub enum Timeout<Time, S> {
+    Running { child: S, remaining: Time },
+    Stopped(Status, Option<Time>),
+}
+
+impl<Time, Out, S> SignalTrans<Time> for Timeout<Time, S>
+where
+    Time: Num + PartialOrd + Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, Status>>,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<Out, Status>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let (child, remaining) = match self {
+            Timeout::Running { child, remaining } => (child, remaining),
+            Timeout::Stopped(status, _) => {
+                return (
+                    Timeout::Stopped(status, Some(delta)),
+                    TaskOutput::Stopped(status),
+                );
+            }
+        };
+
+        // Only run the child up to the time limit
+        let time = if delta < remaining { delta } else { remaining };
+        let (child, val) = child.step(time, input);
+        match val {
+            TaskOutput::Running(val) if delta < remaining => (
+                Timeout::Running {
+                    child,
+                    remaining: remaining - delta,
+                },
+                TaskOutput::Running(val),
+            ),
+            TaskOutput::Running(_) => (
+                Timeout::Stopped(Status::Failure, Some(delta - remaining)),
+                TaskOutput::Stopped(Status::Failure),
+            ),
+            TaskOutput::Stopped(status) => {
+                let leftover =
+                    child.leftover().unwrap_or_else(Time::zero) + delta - time;
+                (
+                    Timeout::Stopped(status, Some(leftover)),
+                    TaskOutput::Stopped(status),
+                )
+            }
+        }
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        match self {
+            Timeout::Running { .. } => None,
+            Timeout::Stopped(_, leftover) => *leftover,
+        }
+    }
+}
+
+pub fn timeout<Time, Out, S>(
+    limit: Time,
+    child: SF<Time, S>,
+) -> SF<Time, Timeout<Time, S>>
+where
+    Time: Num + PartialOrd + Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, Status>>,
+{
+    SF::from(Timeout::Running {
+        child: child.into_inner(),
+        remaining: limit,
+    })
+}
+
+// Runs the child over and over from a clone of its initial state, like
+// repeat_forever(), but waits for period after each success before starting it
+// again so that it isn't run too often. The cooldown is kept here rather than
+// in the child, so restarting the child doesn't lose it, and this never stops.
+// The output is None on steps which end with the child cooling down or waiting
+// to restart.
+#[derive(Copy, Clone)]
+pub struct Cooldown<Time, S> {
+    init: S,
+    child: S,
+    period: Time,
+    // Time left before the child can be started again
+    waiting: Option<Time>,
+}
+
+impl<Time, Out, S> SignalTrans<Time> for Cooldown<Time, S>
+where
+    Time: Num + PartialOrd + Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, Status>> + Clone,
+    S::Input: Clone,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<Option<Out>, Status>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let Cooldown {
+            init,
+            mut child,
+            period,
+            mut waiting,
+        } = self;
+
+        // Each iteration either finishes the step, cools down for a positive
+        // period, or restarts a failed child with less time than the last, so
+        // this always ends
+        let mut time = delta;
+        loop {
+            if let Some(remaining) = waiting {
+                if time <= remaining {
+                    return (
+                        Cooldown {
+                            init,
+                            child,
+                            period,
+                            waiting: Some(remaining - time),
+                        },
+                        TaskOutput::Running(None),
+                    );
+                }
+                time = time - remaining;
+                waiting = None;
+            }
+
+            let (child_next, val) = child.step(time, input.clone());
+            let status = match val {
+                TaskOutput::Running(val) => {
+                    return (
+                        Cooldown {
+                            init,
+                            child: child_next,
+                            period,
+                            waiting,
+                        },
+                        TaskOutput::Running(Some(val)),
+                    );
+                }
+                TaskOutput::Stopped(status) => status,
+            };
+
+            // The cooldown starts from when the child succeeded. A child which
+            // doesn't say how much time it left, or which failed without
+            // taking any, is restarted (or starts cooling down) on the next
+            // step.
+            let leftover = child_next.leftover();
+            child = init.clone();
+            if status.is_success() {
+                waiting = Some(period);
+            }
+            match leftover {
+                Some(leftover) if leftover < time || status.is_success() => {
+                    time = leftover;
+                }
+                _ => {
+                    return (
+                        Cooldown {
+                            init,
+                            child,
+                            period,
+                            waiting,
+                        },
+                        TaskOutput::Running(None),
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub fn cooldown<Time, Out, S>(
+    period: Time,
+    child: SF<Time, S>,
+) -> SF<Time, Cooldown<Time, S>>
+where
+    Time: Num + PartialOrd + Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, Status>> + Clone,
+    S::Input: Clone,
+{
+    assert!(period > Time::zero(), "period must be positive");
+
+    let child = child.into_inner();
+    SF::from(Cooldown {
+        init: child.clone(),
+        child,
+        period,
+        waiting: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::std::cell::Cell;
+
+    use crate::repeat_forever;
+
+    #[test]
+    fn cooldown_under_repeat_forever() {
+        let calls = Rc::new(Cell::new(0));
+        let counted = calls.clone();
+        let check = condition::<f64, (), (), _>(move |_| {
+            counted.set(counted.get() + 1);
+            true
+        });
+
+        // The condition is checked at 0, 10 and 20 seconds
+        let mut sf = repeat_forever(cooldown(10.0, check));
+        for _ in 0..25 {
+            let (next, out) = sf.step(1.0, ());
+            assert!(out.is_running(), "cooldown stopped");
+            sf = next;
+        }
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn cooldown_restarts_failures_each_step() {
+        let calls = Rc::new(Cell::new(0));
+        let counted = calls.clone();
+        let check = condition::<f64, (), (), _>(move |_| {
+            counted.set(counted.get() + 1);
+            false
+        });
+
+        let mut sf = cooldown(10.0, check);
+        for _ in 0..5 {
+            sf = sf.step(1.0, ()).0;
+        }
+        assert_eq!(calls.get(), 5);
+    }
+}
@@ -1,10 +1,12 @@
-use crate::std::marker::PhantomData;
+use crate::std::{
+    iter, marker::PhantomData, ops::BitOr, option, rc::Rc, vec, vec::Vec,
+};
 
-use num_traits::{zero, Num, Signed};
+use num_traits::{zero, Num, Signed, Zero};
 
-use crate::{accum, SignalTrans, SF};
+use crate::{accum, map, map2, Lift, SignalTrans, SF};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Event<T> {
     NoEvent,
     Event(T),
@@ -44,6 +46,122 @@ impl<T> Event<T> {
     pub fn replace<U>(self, new: U) -> Event<U> {
         self.map(move |_| new)
     }
+
+    pub fn is_event(&self) -> bool {
+        matches!(self, Event::Event(_))
+    }
+
+    pub fn is_no_event(&self) -> bool {
+        !self.is_event()
+    }
+
+    pub fn as_ref(&self) -> Event<&T> {
+        match self {
+            Event::Event(value) => Event::new(value),
+            Event::NoEvent => Event::NoEvent,
+        }
+    }
+
+    pub fn as_mut(&mut self) -> Event<&mut T> {
+        match self {
+            Event::Event(value) => Event::new(value),
+            Event::NoEvent => Event::NoEvent,
+        }
+    }
+
+    pub fn and_then<U, F>(self, f: F) -> Event<U>
+    where
+        F: FnOnce(T) -> Event<U>,
+    {
+        match self {
+            Event::Event(value) => f(value),
+            Event::NoEvent => Event::NoEvent,
+        }
+    }
+
+    pub fn filter_map<U, F>(self, f: F) -> Event<U>
+    where
+        F: FnOnce(T) -> Option<U>,
+    {
+        self.and_then(|value| f(value).into())
+    }
+
+    // Left-biased merge
+    pub fn or(self, other: Self) -> Self {
+        match self {
+            Event::Event(_) => self,
+            Event::NoEvent => other,
+        }
+    }
+
+    pub fn or_else<F>(self, f: F) -> Self
+    where
+        F: FnOnce() -> Self,
+    {
+        match self {
+            Event::Event(_) => self,
+            Event::NoEvent => f(),
+        }
+    }
+
+    // Only fires when both events do
+    pub fn zip<U>(self, other: Event<U>) -> Event<(T, U)> {
+        match (self, other) {
+            (Event::Event(a), Event::Event(b)) => Event::new((a, b)),
+            _ => Event::NoEvent,
+        }
+    }
+
+    pub fn unwrap_or(self, default: T) -> T {
+        Option::from(self).unwrap_or(default)
+    }
+
+    pub fn unwrap_or_else<F>(self, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        Option::from(self).unwrap_or_else(f)
+    }
+
+    pub fn iter(&self) -> option::IntoIter<&T> {
+        self.as_ref().into_iter()
+    }
+}
+
+impl<T> From<Option<T>> for Event<T> {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => Event::new(value),
+            None => Event::NoEvent,
+        }
+    }
+}
+
+impl<T> From<Event<T>> for Option<T> {
+    fn from(event: Event<T>) -> Self {
+        match event {
+            Event::Event(value) => Some(value),
+            Event::NoEvent => None,
+        }
+    }
+}
+
+impl<T> IntoIterator for Event<T> {
+    type Item = T;
+    type IntoIter = option::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Option::from(self).into_iter()
+    }
+}
+
+// Left-biased merge, like Event::or
+impl<T> BitOr for Event<T> {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        self.or(other)
+    }
 }
 
 impl<A, B> Event<(A, B)> {
@@ -61,16 +179,180 @@ impl<T> Default for Event<T> {
     }
 }
 
-pub fn fold<Time, A, B: Clone, F>(
+//
+// Events
+//
+
+// Any number of occurrences within one step, each paired with its age (the
+// time between it occurring and the end of the step). Occurrences are kept
+// oldest first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Events<Time, T> {
+    occurrences: Vec<(Time, T)>,
+}
+
+impl<Time, T> Default for Events<Time, T> {
+    fn default() -> Self {
+        Self {
+            occurrences: Vec::new(),
+        }
+    }
+}
+
+impl<Time, T> Events<Time, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn single(age: Time, value: T) -> Self {
+        Self {
+            occurrences: vec![(age, value)],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.occurrences.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.occurrences.is_empty()
+    }
+
+    pub fn iter(&self) -> crate::std::slice::Iter<'_, (Time, T)> {
+        self.occurrences.iter()
+    }
+
+    pub fn map<U, F>(self, mut f: F) -> Events<Time, U>
+    where
+        F: FnMut(T) -> U,
+    {
+        Events {
+            occurrences: self
+                .occurrences
+                .into_iter()
+                .map(|(age, value)| (age, f(value)))
+                .collect(),
+        }
+    }
+
+    pub fn filter<F>(mut self, mut f: F) -> Self
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.occurrences.retain(|(_, value)| f(value));
+        self
+    }
+
+    // Oldest occurrence
+    pub fn first(self) -> Event<T> {
+        self.occurrences
+            .into_iter()
+            .next()
+            .map(|(_, value)| value)
+            .into()
+    }
+
+    // Newest occurrence
+    pub fn last(self) -> Event<T> {
+        self.occurrences
+            .into_iter()
+            .last()
+            .map(|(_, value)| value)
+            .into()
+    }
+}
+
+impl<Time: PartialOrd, T> Events<Time, T> {
+    // Inserts an occurrence, keeping them in order. Occurrences with the same
+    // age stay in insertion order.
+    pub fn push(&mut self, age: Time, value: T) {
+        let index = self
+            .occurrences
+            .iter()
+            .position(|(other, _)| *other < age)
+            .unwrap_or(self.occurrences.len());
+        self.occurrences.insert(index, (age, value));
+    }
+
+    pub fn merge(mut self, other: Self) -> Self {
+        for (age, value) in other.occurrences {
+            self.push(age, value);
+        }
+        self
+    }
+}
+
+impl<Time: Zero, T> From<Event<T>> for Events<Time, T> {
+    fn from(event: Event<T>) -> Self {
+        Events {
+            occurrences: event.into_iter().map(|v| (zero(), v)).collect(),
+        }
+    }
+}
+
+// Occurrences may come in any order
+impl<Time: PartialOrd, T> iter::FromIterator<(Time, T)> for Events<Time, T> {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (Time, T)>,
+    {
+        let mut events = Events::new();
+        for (age, value) in iter {
+            events.push(age, value);
+        }
+        events
+    }
+}
+
+impl<Time, T> IntoIterator for Events<Time, T> {
+    type Item = (Time, T);
+    type IntoIter = vec::IntoIter<(Time, T)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.occurrences.into_iter()
+    }
+}
+
+// Event-carrying types which SFs like fold() and hold() and task
+// stop conditions accept. Occurrences come out oldest first, paired with their
+// age.
+pub trait Occurrences<Time> {
+    type Value;
+    type Iter: Iterator<Item = (Time, Self::Value)>;
+
+    fn occurrences(self) -> Self::Iter;
+}
+
+impl<Time: Zero, T> Occurrences<Time> for Event<T> {
+    type Value = T;
+    type Iter = iter::Map<option::IntoIter<T>, fn(T) -> (Time, T)>;
+
+    fn occurrences(self) -> Self::Iter {
+        self.into_iter().map(|value| (zero(), value))
+    }
+}
+
+impl<Time, T> Occurrences<Time> for Events<Time, T> {
+    type Value = T;
+    type Iter = vec::IntoIter<(Time, T)>;
+
+    fn occurrences(self) -> Self::Iter {
+        self.into_iter()
+    }
+}
+
+pub fn fold<Time, E, B: Clone, F>(
     init: B,
     f: F,
-) -> SF<Time, impl SignalTrans<Time, Input = Event<A>, Output = B>>
+) -> SF<Time, impl SignalTrans<Time, Input = E, Output = B>>
 where
-    F: Fn(A, B) -> B + 'static,
+    E: Occurrences<Time>,
+    F: Fn(E::Value, B) -> B + 'static,
 {
-    accum(init, move |_, ev, acc| match ev {
-        Event::Event(value) => f(value, acc),
-        Event::NoEvent => acc,
+    accum(init, move |_, events: E, acc| {
+        events
+            .occurrences()
+            .fold(acc, |acc, (_, value)| f(value, acc))
     })
 }
 
@@ -132,21 +414,22 @@ impl<Time, A, B> After<Time, A, B> {
     }
 }
 
+// The event's age is how far the step went past the deadline
 impl<Time: Num + Signed, A, B> SignalTrans<Time> for After<Time, A, B> {
     type Input = A;
-    type Output = Event<B>;
+    type Output = Events<Time, B>;
 
-    fn step(self, delta: Time, _: A) -> (Self, Event<B>) {
+    fn step(self, delta: Time, _: A) -> (Self, Events<Time, B>) {
         match self {
             After::NotYet { time, value, .. } => {
                 let remaining = time - delta;
                 if remaining.is_negative() || remaining == zero() {
-                    (After::Past, Event::new(value))
+                    (After::Past, Events::single(-remaining, value))
                 } else {
-                    (Self::new(remaining, value), Event::NoEvent)
+                    (Self::new(remaining, value), Events::new())
                 }
             }
-            After::Past => (After::Past, Event::NoEvent),
+            After::Past => (After::Past, Events::new()),
         }
     }
 }
@@ -159,96 +442,1148 @@ where
 }
 
 //
-// Edge
+// Repeatedly
 //
 
-#[derive(Copy, Clone, Default)]
-pub struct Edge {
-    last: bool,
+// Fires every period, carrying over how far each step overshot the deadline so
+// that the events don't drift. Steps longer than the period contain several
+// occurrences.
+#[derive(Copy, Clone)]
+pub struct Repeatedly<Time, A, B> {
+    period: Time,
+    remaining: Time,
+    value: B,
+    _a: PhantomData<A>,
 }
 
-impl Edge {
-    pub fn new(last: bool) -> Self {
-        Self { last: last }
+impl<Time: Num + Signed + Copy, A, B> Repeatedly<Time, A, B> {
+    pub fn new(period: Time, value: B) -> Self {
+        assert!(period.is_positive(), "period must be positive");
+
+        Self {
+            period,
+            remaining: period,
+            value,
+            _a: Default::default(),
+        }
     }
 }
 
-impl<Time> SignalTrans<Time> for Edge {
-    type Input = bool;
-    type Output = Event<()>;
+impl<Time, A, B> SignalTrans<Time> for Repeatedly<Time, A, B>
+where
+    Time: Num + Signed + PartialOrd + Copy,
+    B: Clone,
+{
+    type Input = A;
+    type Output = Events<Time, B>;
 
-    fn step(self, _: Time, on: bool) -> (Self, Event<()>) {
-        let ev = if !self.last && on {
-            Event::new(())
-        } else {
-            Event::NoEvent
-        };
+    fn step(self, delta: Time, _: A) -> (Self, Events<Time, B>) {
+        let mut remaining = self.remaining - delta;
+        let mut events = Events::new();
+        while remaining.is_negative() || remaining == zero() {
+            events.push(-remaining, self.value.clone());
+            remaining = remaining + self.period;
+        }
+        (Self { remaining, ..self }, events)
+    }
+}
 
-        (Self::new(on), ev)
+pub fn repeatedly<Time, A, B>(
+    period: Time,
+    value: B,
+) -> SF<Time, Repeatedly<Time, A, B>>
+where
+    Time: Num + Signed + PartialOrd + Copy,
+    B: Clone,
+{
+    SF::from(Repeatedly::new(period, value))
+}
+
+// Like repeatedly(), but also fires at the start of the first step, e.g. for
+// heartbeats which shouldn't wait a period before the first one
+pub fn every<Time, A, B>(
+    period: Time,
+    value: B,
+) -> SF<Time, Repeatedly<Time, A, B>>
+where
+    Time: Num + Signed + PartialOrd + Copy,
+    B: Clone,
+{
+    SF::from(Repeatedly {
+        remaining: zero(),
+        ..Repeatedly::new(period, value)
+    })
+}
+
+//
+// AfterEach
+//
+
+// Fires each value in the schedule, with each time being relative to the
+// previous event
+#[derive(Clone)]
+pub struct AfterEach<Time, A, B> {
+    remaining: Time,
+    // Stored in reverse so the next event can be popped off
+    pending: Vec<(Time, B)>,
+    _a: PhantomData<A>,
+}
+
+impl<Time: Num + Copy, A, B> AfterEach<Time, A, B> {
+    pub fn new(schedule: Vec<(Time, B)>) -> Self {
+        let pending: Vec<_> = schedule.into_iter().rev().collect();
+        Self {
+            remaining: pending.last().map_or(zero(), |&(time, _)| time),
+            pending,
+            _a: Default::default(),
+        }
     }
 }
 
-pub fn edge_init<Time>(init: bool) -> SF<Time, Edge> {
-    SF::from(Edge::new(init))
+impl<Time: Num + Signed + PartialOrd + Copy, A, B> SignalTrans<Time>
+    for AfterEach<Time, A, B>
+{
+    type Input = A;
+    type Output = Events<Time, B>;
+
+    fn step(mut self, delta: Time, _: A) -> (Self, Events<Time, B>) {
+        let mut events = Events::new();
+        if self.pending.is_empty() {
+            return (self, events);
+        }
+
+        self.remaining = self.remaining - delta;
+        while self.remaining.is_negative() || self.remaining == zero() {
+            match self.pending.pop() {
+                Some((_, value)) => events.push(-self.remaining, value),
+                None => break,
+            }
+            match self.pending.last() {
+                Some(&(next, _)) => self.remaining = self.remaining + next,
+                None => break,
+            }
+        }
+        (self, events)
+    }
 }
 
-pub fn edge<Time>() -> SF<Time, Edge> {
-    edge_init(false)
+pub fn after_each<Time, A, B>(
+    schedule: Vec<(Time, B)>,
+) -> SF<Time, AfterEach<Time, A, B>>
+where
+    Time: Num + Signed + PartialOrd + Copy,
+{
+    SF::from(AfterEach::new(schedule))
 }
 
 //
-// Hold
-// TODO Implement in terms of fold? (shorter but possibly worse performance)
+// At
 //
 
+// Fires once the time since this SF started reaches the given time, so a
+// scenario can be scheduled in absolute times rather than delays
 #[derive(Copy, Clone)]
-pub struct Hold<A> {
-    held: A,
+pub struct At<Time, A, B> {
+    elapsed: Time,
+    time: Time,
+    value: Option<B>,
+    _a: PhantomData<A>,
 }
 
-impl<A> Hold<A> {
-    pub fn new(init: A) -> Self {
-        Self { held: init }
+impl<Time: Num, A, B> At<Time, A, B> {
+    pub fn new(time: Time, value: B) -> Self {
+        Self {
+            elapsed: zero(),
+            time,
+            value: Some(value),
+            _a: Default::default(),
+        }
     }
 }
 
-impl<Time, A: Clone> SignalTrans<Time> for Hold<A> {
-    type Input = Event<A>;
-    type Output = A;
+// The event's age is how far the step went past the time
+impl<Time, A, B> SignalTrans<Time> for At<Time, A, B>
+where
+    Time: Num + PartialOrd + Copy,
+{
+    type Input = A;
+    type Output = Events<Time, B>;
 
-    fn step(self, _: Time, event: Event<A>) -> (Self, A) {
-        match event {
-            Event::NoEvent => {
-                let value = self.held.clone();
-                (self, value)
+    fn step(mut self, delta: Time, _: A) -> (Self, Events<Time, B>) {
+        self.elapsed = self.elapsed + delta;
+        let events = match self.value.take() {
+            Some(value) if self.elapsed >= self.time => {
+                Events::single(self.elapsed - self.time, value)
             }
-            Event::Event(value) => (Self::new(value.clone()), value),
-        }
+            value => {
+                self.value = value;
+                Events::new()
+            }
+        };
+        (self, events)
     }
 }
 
-pub fn hold<Time, A: Clone>(init: A) -> SF<Time, Hold<A>> {
-    SF::from(Hold::new(init))
+pub fn at<Time, A, B>(time: Time, value: B) -> SF<Time, At<Time, A, B>>
+where
+    Time: Num + PartialOrd + Copy,
+{
+    SF::from(At::new(time, value))
 }
 
 //
-// Switcher
+// Debounce
 //
 
-/*
-pub struct Switcher {
+// Only passes an event once no other events have arrived for the window,
+// outputting the latest value
+#[derive(Copy, Clone)]
+pub struct Debounce<Time, A, E> {
+    window: Time,
+    pending: After<Time, (), A>,
+    _e: PhantomData<E>,
+}
 
+impl<Time, A, E> Debounce<Time, A, E> {
+    pub fn new(window: Time) -> Self {
+        Self {
+            window,
+            pending: After::Past,
+            _e: Default::default(),
+        }
+    }
 }
 
-pub fn switcher<A, B, C, T: Clone, U>(init: SF<Time, T>)
--> SF<Time, impl SignalTrans<Time, Input = (A, Event<C>), Output = B>>
+impl<Time, A, E> SignalTrans<Time> for Debounce<Time, A, E>
 where
-    T: SignalTrans<Time, Input = A, Output = C>,
-    // U: SignalTrans<Time, Input = Event<C>, Output = SF<Time, T>>,
+    Time: Num + Signed + PartialOrd + Copy,
+    E: Occurrences<Time, Value = A>,
 {
-    let input = init.clone() | map2(|a, _| a);
-    let event = init | map2(|_, e| e);
+    type Input = E;
+    type Output = Events<Time, A>;
+
+    fn step(self, delta: Time, events: E) -> (Self, Events<Time, A>) {
+        let Debounce {
+            window,
+            mut pending,
+            _e,
+        } = self;
+        let mut out = Events::new();
 
+        // Runs the window up to each occurrence, which then restarts it
+        let mut start = delta;
+        for (age, value) in events.occurrences() {
+            let (_, fired) = pending.step(start - age, ());
+            for (fired_age, value) in fired {
+                out.push(fired_age + age, value);
+            }
+            pending = After::new(window, value);
+            start = age;
+        }
 
+        let (pending, fired) = pending.step(start, ());
+        for (age, value) in fired {
+            out.push(age, value);
+        }
+        (
+            Debounce {
+                window,
+                pending,
+                _e,
+            },
+            out,
+        )
+    }
+}
+
+pub fn debounce<Time, A, E>(window: Time) -> SF<Time, Debounce<Time, A, E>>
+where
+    Time: Num + Signed + PartialOrd + Copy,
+    E: Occurrences<Time, Value = A>,
+{
+    SF::from(Debounce::new(window))
+}
+
+// Only changes the output once the input has held a new value for the window
+#[derive(Copy, Clone)]
+pub struct DebounceSignal<Time, A> {
+    window: Time,
+    stable: A,
+    pending: After<Time, (), A>,
+}
+
+impl<Time, A> DebounceSignal<Time, A> {
+    pub fn new(window: Time, init: A) -> Self {
+        Self {
+            window,
+            stable: init,
+            pending: After::Past,
+        }
+    }
+}
+
+impl<Time, A> SignalTrans<Time> for DebounceSignal<Time, A>
+where
+    Time: Num + Signed + Copy,
+    A: PartialEq + Clone,
+{
+    type Input = A;
+    type Output = A;
+
+    fn step(self, delta: Time, input: A) -> (Self, A) {
+        let DebounceSignal {
+            window,
+            stable,
+            pending,
+        } = self;
+
+        let (stable, pending) = if input == stable {
+            (stable, After::Past)
+        } else {
+            match pending {
+                // Restart the window whenever the input changes
+                After::NotYet { ref value, .. } if *value == input => {
+                    let (pending, out) = pending.step(delta, ());
+                    match out.first() {
+                        Event::Event(value) => (value, After::Past),
+                        Event::NoEvent => (stable, pending),
+                    }
+                }
+                _ => (stable, After::new(window, input)),
+            }
+        };
+
+        let out = stable.clone();
+        (
+            DebounceSignal {
+                window,
+                stable,
+                pending,
+            },
+            out,
+        )
+    }
+}
+
+pub fn debounce_signal<Time, A>(
+    window: Time,
+    init: A,
+) -> SF<Time, DebounceSignal<Time, A>>
+where
+    Time: Num + Signed + Copy,
+    A: PartialEq + Clone,
+{
+    SF::from(DebounceSignal::new(window, init))
+}
+
+//
+// Throttle
+//
+
+// Passes at most one event per period, dropping the rest
+#[derive(Copy, Clone)]
+pub struct Throttle<Time, E> {
+    period: Time,
+    cooldown: After<Time, (), ()>,
+    ready: bool,
+    _e: PhantomData<E>,
+}
+
+impl<Time, E> Throttle<Time, E> {
+    pub fn new(period: Time) -> Self {
+        Self {
+            period,
+            cooldown: After::Past,
+            ready: true,
+            _e: Default::default(),
+        }
+    }
+}
+
+impl<Time, E> SignalTrans<Time> for Throttle<Time, E>
+where
+    Time: Num + Signed + PartialOrd + Copy,
+    E: Occurrences<Time>,
+{
+    type Input = E;
+    type Output = Events<Time, E::Value>;
+
+    fn step(self, delta: Time, events: E) -> (Self, Self::Output) {
+        let Throttle {
+            period,
+            mut cooldown,
+            mut ready,
+            _e,
+        } = self;
+        let mut out = Events::new();
+
+        // Runs the cooldown up to each occurrence, restarting it from the ones
+        // which pass
+        let mut start = delta;
+        for (age, value) in events.occurrences() {
+            let (next, done) = cooldown.step(start - age, ());
+            cooldown = next;
+            ready = ready || !done.is_empty();
+            if ready {
+                out.push(age, value);
+                cooldown = After::new(period, ());
+                ready = false;
+            }
+            start = age;
+        }
+
+        let (cooldown, done) = cooldown.step(start, ());
+        let ready = ready || !done.is_empty();
+        (
+            Throttle {
+                period,
+                cooldown,
+                ready,
+                _e,
+            },
+            out,
+        )
+    }
+}
+
+pub fn throttle<Time, E>(period: Time) -> SF<Time, Throttle<Time, E>>
+where
+    Time: Num + Signed + PartialOrd + Copy,
+    E: Occurrences<Time>,
+{
+    SF::from(Throttle::new(period))
+}
+
+//
+// HeldFor
+//
+
+// Fires once the input has been true for the duration, and again only after
+// it has gone false
+#[derive(Copy, Clone)]
+pub struct HeldFor<Time> {
+    duration: Time,
+    timer: After<Time, (), ()>,
+}
+
+impl<Time: Copy> HeldFor<Time> {
+    pub fn new(duration: Time) -> Self {
+        Self {
+            duration,
+            timer: After::new(duration, ()),
+        }
+    }
+}
+
+impl<Time: Num + Signed + Copy> SignalTrans<Time> for HeldFor<Time> {
+    type Input = bool;
+    type Output = Event<()>;
+
+    fn step(self, delta: Time, on: bool) -> (Self, Event<()>) {
+        if on {
+            let (timer, out) = self.timer.step(delta, ());
+            (HeldFor { timer, ..self }, out.first())
+        } else {
+            (Self::new(self.duration), Event::NoEvent)
+        }
+    }
+}
+
+pub fn held_for<Time>(duration: Time) -> SF<Time, HeldFor<Time>>
+where
+    Time: Num + Signed + Copy,
+{
+    SF::from(HeldFor::new(duration))
+}
+
+//
+// Edge
+//
+
+#[derive(Copy, Clone, Default)]
+pub struct Edge {
+    last: bool,
+}
+
+impl Edge {
+    pub fn new(last: bool) -> Self {
+        Self { last: last }
+    }
+}
+
+impl<Time> SignalTrans<Time> for Edge {
+    type Input = bool;
+    type Output = Event<()>;
+
+    fn step(self, _: Time, on: bool) -> (Self, Event<()>) {
+        let ev = if !self.last && on {
+            Event::new(())
+        } else {
+            Event::NoEvent
+        };
+
+        (Self::new(on), ev)
+    }
+}
+
+pub fn edge_init<Time>(init: bool) -> SF<Time, Edge> {
+    SF::from(Edge::new(init))
+}
+
+pub fn edge<Time>() -> SF<Time, Edge> {
+    edge_init(false)
+}
+
+// Fires when the input goes from true to false
+#[derive(Copy, Clone, Default)]
+pub struct FallingEdge {
+    last: bool,
+}
+
+impl FallingEdge {
+    pub fn new(last: bool) -> Self {
+        Self { last }
+    }
+}
+
+impl<Time> SignalTrans<Time> for FallingEdge {
+    type Input = bool;
+    type Output = Event<()>;
+
+    fn step(self, _: Time, on: bool) -> (Self, Event<()>) {
+        let ev = if self.last && !on {
+            Event::new(())
+        } else {
+            Event::NoEvent
+        };
+
+        (Self::new(on), ev)
+    }
+}
+
+pub fn falling_edge_init<Time>(init: bool) -> SF<Time, FallingEdge> {
+    SF::from(FallingEdge::new(init))
+}
+
+pub fn falling_edge<Time>() -> SF<Time, FallingEdge> {
+    falling_edge_init(false)
+}
+
+// Fires on both rising and falling edges
+#[derive(Copy, Clone, Default)]
+pub struct AnyEdge {
+    last: bool,
+}
+
+impl AnyEdge {
+    pub fn new(last: bool) -> Self {
+        Self { last }
+    }
+}
+
+impl<Time> SignalTrans<Time> for AnyEdge {
+    type Input = bool;
+    type Output = Event<()>;
+
+    fn step(self, _: Time, on: bool) -> (Self, Event<()>) {
+        let ev = if self.last != on {
+            Event::new(())
+        } else {
+            Event::NoEvent
+        };
+
+        (Self::new(on), ev)
+    }
+}
+
+pub fn any_edge_init<Time>(init: bool) -> SF<Time, AnyEdge> {
+    SF::from(AnyEdge::new(init))
+}
+
+pub fn any_edge<Time>() -> SF<Time, AnyEdge> {
+    any_edge_init(false)
+}
+
+//
+// EdgeBy
+//
+
+type EdgePred<A> = Rc<dyn Fn(&A, &A) -> bool>;
+
+// Fires with the new value whenever pred(last, new) holds. Without an initial
+// value, the first input never fires.
+#[derive(Clone)]
+pub struct EdgeBy<A> {
+    last: Option<A>,
+    pred: EdgePred<A>,
+}
+
+impl<A> EdgeBy<A> {
+    pub fn new<F>(last: Option<A>, pred: F) -> Self
+    where
+        F: Fn(&A, &A) -> bool + 'static,
+    {
+        Self {
+            last,
+            pred: Rc::new(pred),
+        }
+    }
+}
+
+impl<Time, A: Clone> SignalTrans<Time> for EdgeBy<A> {
+    type Input = A;
+    type Output = Event<A>;
+
+    fn step(self, _: Time, value: A) -> (Self, Event<A>) {
+        let EdgeBy { last, pred } = self;
+        let ev = match last {
+            Some(last) if pred(&last, &value) => Event::new(value.clone()),
+            _ => Event::NoEvent,
+        };
+
+        (
+            EdgeBy {
+                last: Some(value),
+                pred,
+            },
+            ev,
+        )
+    }
+}
+
+pub fn edge_by_init<Time, A, F>(init: A, pred: F) -> SF<Time, EdgeBy<A>>
+where
+    A: Clone,
+    F: Fn(&A, &A) -> bool + 'static,
+{
+    SF::from(EdgeBy::new(Some(init), pred))
+}
+
+pub fn edge_by<Time, A, F>(pred: F) -> SF<Time, EdgeBy<A>>
+where
+    A: Clone,
+    F: Fn(&A, &A) -> bool + 'static,
+{
+    SF::from(EdgeBy::new(None, pred))
+}
+
+pub fn changed_init<Time, A>(init: A) -> SF<Time, EdgeBy<A>>
+where
+    A: PartialEq + Clone,
+{
+    edge_by_init(init, |last, value| last != value)
+}
+
+pub fn changed<Time, A>() -> SF<Time, EdgeBy<A>>
+where
+    A: PartialEq + Clone,
+{
+    edge_by(|last: &A, value: &A| last != value)
+}
+
+//
+// Hold
+// TODO Implement in terms of fold? (shorter but possibly worse performance)
+//
+
+// Holds the newest occurrence, so any kind of events can be held (e.g. Events
+// from after())
+#[derive(Copy, Clone)]
+pub struct Hold<A, E> {
+    held: A,
+    _e: PhantomData<E>,
+}
+
+impl<A, E> Hold<A, E> {
+    pub fn new(init: A) -> Self {
+        Self {
+            held: init,
+            _e: Default::default(),
+        }
+    }
+}
+
+impl<Time, A, E> SignalTrans<Time> for Hold<A, E>
+where
+    A: Clone,
+    E: Occurrences<Time, Value = A>,
+{
+    type Input = E;
+    type Output = A;
+
+    fn step(self, _: Time, events: E) -> (Self, A) {
+        match events.occurrences().last() {
+            None => {
+                let value = self.held.clone();
+                (self, value)
+            }
+            Some((_, value)) => (Self::new(value.clone()), value),
+        }
+    }
+}
+
+pub fn hold<Time, A, E>(init: A) -> SF<Time, Hold<A, E>>
+where
+    A: Clone,
+    E: Occurrences<Time, Value = A>,
+{
+    SF::from(Hold::new(init))
+}
+
+//
+// Merge
+//
+
+pub type MergeEvents<Time, L, R, A> = Lift<Time, (L, R), Events<Time, A>>;
+
+// Combines simultaneous events (ones with the same age) with f
+pub fn merge_by<Time, A, L, R, F>(f: F) -> SF<Time, MergeEvents<Time, L, R, A>>
+where
+    Time: PartialOrd,
+    L: Occurrences<Time, Value = A>,
+    R: Occurrences<Time, Value = A>,
+    F: Fn(A, A) -> A + 'static,
+{
+    map2(move |left: L, right: R| {
+        let mut merged = Events::new();
+        let mut right = right.occurrences().peekable();
+        for (age, l) in left.occurrences() {
+            // Older occurrences on the right come first
+            while let Some((r_age, r)) =
+                right.next_if(|(r_age, _)| *r_age > age)
+            {
+                merged.push(r_age, r);
+            }
+            match right.next_if(|(r_age, _)| *r_age == age) {
+                Some((_, r)) => merged.push(age, f(l, r)),
+                None => merged.push(age, l),
+            }
+        }
+        for (age, r) in right {
+            merged.push(age, r);
+        }
+        merged
+    })
+}
+
+pub fn merge_left<Time, A, L, R>() -> SF<Time, MergeEvents<Time, L, R, A>>
+where
+    Time: PartialOrd,
+    L: Occurrences<Time, Value = A>,
+    R: Occurrences<Time, Value = A>,
+{
+    merge_by(|l, _| l)
+}
+
+pub fn merge_right<Time, A, L, R>() -> SF<Time, MergeEvents<Time, L, R, A>>
+where
+    Time: PartialOrd,
+    L: Occurrences<Time, Value = A>,
+    R: Occurrences<Time, Value = A>,
+{
+    merge_by(|_, r| r)
+}
+
+//
+// Tag / Gate
+//
+
+pub fn tag<Time, E, B>(value: B) -> SF<Time, Lift<Time, E, Events<Time, B>>>
+where
+    Time: PartialOrd,
+    E: Occurrences<Time>,
+    B: Clone + 'static,
+{
+    map(move |events: E| {
+        events
+            .occurrences()
+            .map(|(age, _)| (age, value.clone()))
+            .collect()
+    })
+}
+
+// Combines events with the current value of a signal
+pub type EventWithSignal<Time, E, B, C> = Lift<Time, (E, B), Events<Time, C>>;
+
+// Only passes events while the input signal is true
+pub fn gate<Time, A, E>() -> SF<Time, EventWithSignal<Time, E, bool, A>>
+where
+    Time: PartialOrd,
+    E: Occurrences<Time, Value = A>,
+{
+    map2(|events: E, open| events.occurrences().filter(|_| open).collect())
+}
+
+//
+// Take / Drop
+//
+
+#[derive(Copy, Clone)]
+pub struct TakeEvents<E> {
+    remaining: usize,
+    _e: PhantomData<E>,
+}
+
+impl<E> TakeEvents<E> {
+    pub fn new(count: usize) -> Self {
+        Self {
+            remaining: count,
+            _e: Default::default(),
+        }
+    }
+}
+
+impl<Time: PartialOrd, E: Occurrences<Time>> SignalTrans<Time>
+    for TakeEvents<E>
+{
+    type Input = E;
+    type Output = Events<Time, E::Value>;
+
+    fn step(self, _: Time, events: E) -> (Self, Self::Output) {
+        let taken: Events<_, _> =
+            events.occurrences().take(self.remaining).collect();
+        (Self::new(self.remaining - taken.len()), taken)
+    }
+}
+
+pub fn take<Time, E>(count: usize) -> SF<Time, TakeEvents<E>>
+where
+    Time: PartialOrd,
+    E: Occurrences<Time>,
+{
+    SF::from(TakeEvents::new(count))
+}
+
+pub fn once<Time, E>() -> SF<Time, TakeEvents<E>>
+where
+    Time: PartialOrd,
+    E: Occurrences<Time>,
+{
+    take(1)
+}
+
+#[derive(Copy, Clone)]
+pub struct DropEvents<E> {
+    remaining: usize,
+    _e: PhantomData<E>,
+}
+
+impl<E> DropEvents<E> {
+    pub fn new(count: usize) -> Self {
+        Self {
+            remaining: count,
+            _e: Default::default(),
+        }
+    }
+}
+
+impl<Time: PartialOrd, E: Occurrences<Time>> SignalTrans<Time>
+    for DropEvents<E>
+{
+    type Input = E;
+    type Output = Events<Time, E::Value>;
+
+    fn step(self, _: Time, events: E) -> (Self, Self::Output) {
+        let mut remaining = self.remaining;
+        let kept = events
+            .occurrences()
+            .filter(|_| {
+                let drop = remaining > 0;
+                remaining = remaining.saturating_sub(1);
+                !drop
+            })
+            .collect();
+        (Self::new(remaining), kept)
+    }
+}
+
+pub fn drop_events<Time, E>(count: usize) -> SF<Time, DropEvents<E>>
+where
+    Time: PartialOrd,
+    E: Occurrences<Time>,
+{
+    SF::from(DropEvents::new(count))
+}
+
+//
+// AccumBy
+//
+
+// Like fold, but outputs the accumulated value with each event instead of
+// holding it
+#[derive(Clone)]
+pub struct AccumBy<A, B, E> {
+    value: B,
+    f: Rc<dyn Fn(A, B) -> B>,
+    _e: PhantomData<E>,
+}
+
+impl<A, B, E> AccumBy<A, B, E> {
+    pub fn new<F>(init: B, f: F) -> Self
+    where
+        F: Fn(A, B) -> B + 'static,
+    {
+        Self {
+            value: init,
+            f: Rc::new(f),
+            _e: Default::default(),
+        }
+    }
+}
+
+impl<Time, A, B, E> SignalTrans<Time> for AccumBy<A, B, E>
+where
+    Time: PartialOrd,
+    B: Clone,
+    E: Occurrences<Time, Value = A>,
+{
+    type Input = E;
+    type Output = Events<Time, B>;
+
+    fn step(self, _: Time, events: E) -> (Self, Events<Time, B>) {
+        let AccumBy { mut value, f, _e } = self;
+        let mut out = Events::new();
+        for (age, a) in events.occurrences() {
+            value = f(a, value);
+            out.push(age, value.clone());
+        }
+        (AccumBy { value, f, _e }, out)
+    }
+}
+
+pub fn accum_by<Time, A, B, E, F>(init: B, f: F) -> SF<Time, AccumBy<A, B, E>>
+where
+    Time: PartialOrd,
+    B: Clone,
+    E: Occurrences<Time, Value = A>,
+    F: Fn(A, B) -> B + 'static,
+{
+    SF::from(AccumBy::new(init, f))
+}
+
+//
+// Snapshot / Sample
+//
+
+pub type Snapshot<Time, E, A, B> = EventWithSignal<Time, E, B, (A, B)>;
+
+// Pairs each event with the current value of the signal
+pub fn snapshot<Time, A, B, E>() -> SF<Time, Snapshot<Time, E, A, B>>
+where
+    Time: PartialOrd,
+    B: Clone,
+    E: Occurrences<Time, Value = A>,
+{
+    map2(|events: E, b: B| {
+        events
+            .occurrences()
+            .map(|(age, a)| (age, (a, b.clone())))
+            .collect()
+    })
+}
+
+// Replaces each event with the current value of the signal
+pub fn sample<Time, A, B, E>() -> SF<Time, EventWithSignal<Time, E, B, B>>
+where
+    Time: PartialOrd,
+    B: Clone,
+    E: Occurrences<Time, Value = A>,
+{
+    map2(|events: E, b: B| {
+        events
+            .occurrences()
+            .map(|(age, _)| (age, b.clone()))
+            .collect()
+    })
+}
+
+//
+// Switcher
+//
+
+/*
+pub struct Switcher {
+
+}
+
+pub fn switcher<A, B, C, T: Clone, U>(init: SF<Time, T>)
+-> SF<Time, impl SignalTrans<Time, Input = (A, Event<C>), Output = B>>
+where
+    T: SignalTrans<Time, Input = A, Output = C>,
+    // U: SignalTrans<Time, Input = Event<C>, Output = SF<Time, T>>,
+{
+    let input = init.clone() | map2(|a, _| a);
+    let event = init | map2(|_, e| e);
+
+
+}
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::std::vec::Vec;
+
+    use crate::identity;
+
+    fn run<S>(mut sf: SF<f64, S>, steps: Vec<(f64, S::Input)>) -> Vec<S::Output>
+    where
+        S: SignalTrans<f64>,
+    {
+        let mut outputs = Vec::new();
+        for (delta, input) in steps {
+            let (next, out) = sf.step(delta, input);
+            sf = next;
+            outputs.push(out);
+        }
+        outputs
+    }
+
+    fn events<T>(occurrences: Vec<(f64, T)>) -> Events<f64, T> {
+        occurrences.into_iter().collect()
+    }
+
+    #[test]
+    fn edges() {
+        let steps = vec![(1.0, true), (1.0, true), (1.0, false), (1.0, true)];
+        let fire = Event::new(());
+        let none = Event::NoEvent;
+
+        assert_eq!(run(edge(), steps.clone()), vec![fire, none, none, fire]);
+        assert_eq!(
+            run(falling_edge(), steps.clone()),
+            vec![none, none, fire, none],
+        );
+        assert_eq!(run(any_edge(), steps), vec![fire, none, fire, fire]);
+    }
+
+    #[test]
+    fn tag_timed() {
+        assert_eq!(
+            run(repeatedly(0.5, ()) | tag("hb"), vec![(1.25, ())]),
+            vec![events(vec![(0.75, "hb"), (0.25, "hb")])],
+        );
+    }
+
+    #[test]
+    fn hold_timed() {
+        assert_eq!(
+            run(
+                after(1.0, 5) | hold(0),
+                vec![(0.5, ()), (0.75, ()), (0.5, ())]
+            ),
+            vec![0, 5, 5],
+        );
+        assert_eq!(
+            run(repeatedly(0.5, 1) | fold(0, |a, b| a + b), vec![(1.0, ())]),
+            vec![2],
+        );
+    }
+
+    #[test]
+    fn gate_timed() {
+        assert_eq!(
+            run(
+                (repeatedly(0.5, ()) & identity()) | gate(),
+                vec![(0.5, true), (0.5, false)],
+            ),
+            vec![events(vec![(0.0, ())]), events(vec![])],
+        );
+    }
+
+    #[test]
+    fn take_and_drop_timed() {
+        assert_eq!(
+            run(
+                repeatedly(0.5, 1) | take(3),
+                vec![(1.0, ()), (1.0, ()), (1.0, ())],
+            ),
+            vec![
+                events(vec![(0.5, 1), (0.0, 1)]),
+                events(vec![(0.5, 1)]),
+                events(vec![]),
+            ],
+        );
+        assert_eq!(
+            run(every(0.5, ()) | once(), vec![(0.25, ()), (1.0, ())]),
+            vec![events(vec![(0.25, ())]), events(vec![])],
+        );
+        assert_eq!(
+            run(repeatedly(0.5, ()) | drop_events(1), vec![(1.0, ())]),
+            vec![events(vec![(0.0, ())])],
+        );
+    }
+
+    #[test]
+    fn merge_timed() {
+        assert_eq!(
+            run(
+                (after(1.0, 1) & repeatedly(0.5, 2)) | merge_by(|l, r| l + r),
+                vec![(1.25, ())],
+            ),
+            vec![events(vec![(0.75, 2), (0.25, 3)])],
+        );
+        assert_eq!(
+            run(
+                (after(0.5, 1) & at(0.75, 2)) | merge_left(),
+                vec![(1.0, ())]
+            ),
+            vec![events(vec![(0.5, 1), (0.25, 2)])],
+        );
+        assert_eq!(
+            run(
+                (after(0.5, 1) & at(0.5, 2)) | merge_right(),
+                vec![(1.0, ())]
+            ),
+            vec![events(vec![(0.5, 2)])],
+        );
+    }
+
+    #[test]
+    fn accum_by_timed() {
+        assert_eq!(
+            run(
+                after_each(vec![(0.25, 1), (0.25, 2), (0.5, 3)])
+                    | accum_by(0, |a, b| a + b),
+                vec![(1.0, ())],
+            ),
+            vec![events(vec![(0.75, 1), (0.5, 3), (0.0, 6)])],
+        );
+    }
+
+    #[test]
+    fn snapshot_and_sample_timed() {
+        assert_eq!(
+            run((at(0.5, "a") & identity()) | snapshot(), vec![(0.75, 7)]),
+            vec![events(vec![(0.25, ("a", 7))])],
+        );
+        assert_eq!(
+            run(
+                (repeatedly(0.5, ()) & identity()) | sample(),
+                vec![(1.0, 3)]
+            ),
+            vec![events(vec![(0.5, 3), (0.0, 3)])],
+        );
+    }
+
+    #[test]
+    fn debounce_timed() {
+        // The first value settles within the step, before the second arrives
+        assert_eq!(
+            run(
+                after_each(vec![(0.25, 1), (0.75, 2)]) | debounce(0.5),
+                vec![(1.0, ()), (0.5, ())],
+            ),
+            vec![events(vec![(0.25, 1)]), events(vec![(0.0, 2)])],
+        );
+    }
+
+    #[test]
+    fn throttle_timed() {
+        assert_eq!(
+            run(repeatedly(0.25, ()) | throttle(0.5), vec![(1.0, ())]),
+            vec![events(vec![(0.75, ()), (0.25, ())])],
+        );
+    }
 }
-*/
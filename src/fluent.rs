@@ -0,0 +1,159 @@
+use num_traits::{Num, Signed};
+
+use crate::{
+    compose, delay, edge, fanout, hold, integral, map, task, timeout,
+    BasicTask, Compose, Delay, Edge, Fanout, Hold, Integral, MapOutput,
+    Occurrences, SignalTrans, TaskOutput, Timeout, VectorSpace, SF,
+};
+
+// Method versions of the common combinators so that SFs can be built by
+// chaining, e.g. sf.map(f).edge() instead of sf | map(f) | edge()
+pub trait SFExt<Time: Copy, S: SignalTrans<Time>> {
+    // Same as |
+    fn then<U>(self, other: SF<Time, U>) -> SF<Time, Compose<S, U>>
+    where
+        U: SignalTrans<Time, Input = S::Output>;
+
+    fn map<B, F>(self, f: F) -> SF<Time, MapOutput<Time, S, B>>
+    where
+        F: Fn(S::Output) -> B + 'static;
+
+    // Same as &
+    fn fanout<U>(self, other: SF<Time, U>) -> SF<Time, Fanout<Time, S, U>>
+    where
+        U: SignalTrans<Time, Input = S::Input>,
+        S::Input: Clone;
+
+    fn hold<A>(self, init: A) -> SF<Time, Compose<S, Hold<A, S::Output>>>
+    where
+        A: Clone,
+        S::Output: Occurrences<Time, Value = A>;
+
+    fn edge(self) -> SF<Time, Compose<S, Edge>>
+    where
+        S: SignalTrans<Time, Output = bool>;
+
+    fn integrate(self) -> SF<Time, Compose<S, Integral<Time, S::Output>>>
+    where
+        Time: Into<<S::Output as VectorSpace>::Scalar>,
+        S::Output: VectorSpace;
+
+    fn delay(self, init: S::Output) -> SF<Time, Compose<S, Delay<S::Output>>>;
+
+    // Calls f with each output (e.g. for logging), passing it on unchanged
+    fn tap<F>(self, f: F) -> SF<Time, MapOutput<Time, S, S::Output>>
+    where
+        F: Fn(&S::Output) + 'static;
+
+    // Makes a task which runs this until stop has an event
+    fn until<End, T>(
+        self,
+        stop: SF<Time, T>,
+    ) -> SF<Time, BasicTask<Time, End, S, T>>
+    where
+        T: SignalTrans<Time, Input = S::Input>,
+        T::Output: Occurrences<Time, Value = End>,
+        S::Input: Clone,
+        End: Clone;
+
+    // Stops this task with None after time if it hasn't stopped by then
+    fn with_timeout<Out, End>(
+        self,
+        time: Time,
+    ) -> SF<Time, Timeout<Time, Out, End, S>>
+    where
+        Time: Signed + Num,
+        S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
+        S::Input: Clone,
+        End: Clone;
+}
+
+impl<Time: Copy, S: SignalTrans<Time>> SFExt<Time, S> for SF<Time, S> {
+    fn then<U>(self, other: SF<Time, U>) -> SF<Time, Compose<S, U>>
+    where
+        U: SignalTrans<Time, Input = S::Output>,
+    {
+        compose(self, other)
+    }
+
+    fn map<B, F>(self, f: F) -> SF<Time, MapOutput<Time, S, B>>
+    where
+        F: Fn(S::Output) -> B + 'static,
+    {
+        compose(self, map(f))
+    }
+
+    fn fanout<U>(self, other: SF<Time, U>) -> SF<Time, Fanout<Time, S, U>>
+    where
+        U: SignalTrans<Time, Input = S::Input>,
+        S::Input: Clone,
+    {
+        fanout(self, other)
+    }
+
+    fn hold<A>(self, init: A) -> SF<Time, Compose<S, Hold<A, S::Output>>>
+    where
+        A: Clone,
+        S::Output: Occurrences<Time, Value = A>,
+    {
+        compose(self, hold(init))
+    }
+
+    fn edge(self) -> SF<Time, Compose<S, Edge>>
+    where
+        S: SignalTrans<Time, Output = bool>,
+    {
+        compose(self, edge())
+    }
+
+    fn integrate(self) -> SF<Time, Compose<S, Integral<Time, S::Output>>>
+    where
+        Time: Into<<S::Output as VectorSpace>::Scalar>,
+        S::Output: VectorSpace,
+    {
+        compose(self, integral())
+    }
+
+    fn delay(self, init: S::Output) -> SF<Time, Compose<S, Delay<S::Output>>> {
+        compose(self, delay(init))
+    }
+
+    fn tap<F>(self, f: F) -> SF<Time, MapOutput<Time, S, S::Output>>
+    where
+        F: Fn(&S::Output) + 'static,
+    {
+        compose(
+            self,
+            map(move |output| {
+                f(&output);
+                output
+            }),
+        )
+    }
+
+    fn until<End, T>(
+        self,
+        stop: SF<Time, T>,
+    ) -> SF<Time, BasicTask<Time, End, S, T>>
+    where
+        T: SignalTrans<Time, Input = S::Input>,
+        T::Output: Occurrences<Time, Value = End>,
+        S::Input: Clone,
+        End: Clone,
+    {
+        task(self, stop)
+    }
+
+    fn with_timeout<Out, End>(
+        self,
+        time: Time,
+    ) -> SF<Time, Timeout<Time, Out, End, S>>
+    where
+        Time: Signed + Num,
+        S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
+        S::Input: Clone,
+        End: Clone,
+    {
+        timeout(self, time)
+    }
+}
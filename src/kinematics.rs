@@ -0,0 +1,344 @@
+use crate::std::ops::{Add, Mul, Neg, Sub};
+
+use num_traits::{one, zero, Float, FloatConst, Num};
+
+use crate::{
+    accum, lti::mat_solve, map, Accum, Compose, Lift, VectorSpace, SF,
+};
+
+// Wraps an angle to (-pi, pi]
+pub fn normalize_angle<T: Float + FloatConst>(angle: T) -> T {
+    let two_pi = T::PI() + T::PI();
+    let wrapped = angle - two_pi * (angle / two_pi).round();
+    if wrapped <= -T::PI() {
+        wrapped + two_pi
+    } else {
+        wrapped
+    }
+}
+
+//
+// Pose
+//
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Pose<T> {
+    pub x: T,
+    pub y: T,
+    pub theta: T,
+}
+
+impl<T: Float + FloatConst> Pose<T> {
+    pub fn new(x: T, y: T, theta: T) -> Self {
+        Self { x, y, theta }
+    }
+
+    // Applies other in the frame of self
+    pub fn compose(&self, other: &Self) -> Self {
+        let (x, y) = self.to_global(other.x, other.y);
+        Self::new(x, y, normalize_angle(self.theta + other.theta))
+    }
+
+    pub fn inverse(&self) -> Self {
+        let (sin, cos) = self.theta.sin_cos();
+        Self::new(
+            -self.x * cos - self.y * sin,
+            self.x * sin - self.y * cos,
+            normalize_angle(-self.theta),
+        )
+    }
+
+    // Pose of self in the frame of other
+    pub fn relative_to(&self, other: &Self) -> Self {
+        other.inverse().compose(self)
+    }
+
+    // Transforms a point in the local frame into the global frame
+    pub fn to_global(&self, x: T, y: T) -> (T, T) {
+        let (sin, cos) = self.theta.sin_cos();
+        (self.x + x * cos - y * sin, self.y + x * sin + y * cos)
+    }
+
+    // Transforms a point in the global frame into the local frame
+    pub fn to_local(&self, x: T, y: T) -> (T, T) {
+        let (sin, cos) = self.theta.sin_cos();
+        let (dx, dy) = (x - self.x, y - self.y);
+        (dx * cos + dy * sin, -dx * sin + dy * cos)
+    }
+
+    // Moves along a constant twist (in the local frame) for dt using the SE(2)
+    // exponential map, so arcs are followed exactly
+    pub fn integrate(&self, twist: Twist<T>, dt: T) -> Self {
+        let dtheta = twist.omega * dt;
+
+        // sin(x) / x and (1 - cos(x)) / x, using the series near 0
+        let (s, c) = if dtheta.abs() < T::epsilon().sqrt() {
+            let six = T::from(6).unwrap();
+            (
+                one::<T>() - dtheta * dtheta / six,
+                dtheta / (one::<T>() + one()),
+            )
+        } else {
+            (dtheta.sin() / dtheta, (one::<T>() - dtheta.cos()) / dtheta)
+        };
+
+        let dx = (twist.vx * s - twist.vy * c) * dt;
+        let dy = (twist.vx * c + twist.vy * s) * dt;
+        self.compose(&Self::new(dx, dy, dtheta))
+    }
+}
+
+//
+// Twist
+//
+
+// Velocity in the robot frame
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Twist<T> {
+    pub vx: T,
+    pub vy: T,
+    pub omega: T,
+}
+
+impl<T> Twist<T> {
+    pub fn new(vx: T, vy: T, omega: T) -> Self {
+        Self { vx, vy, omega }
+    }
+}
+
+impl<T: Add<Output = T>> Add for Twist<T> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(
+            self.vx + other.vx,
+            self.vy + other.vy,
+            self.omega + other.omega,
+        )
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Twist<T> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(
+            self.vx - other.vx,
+            self.vy - other.vy,
+            self.omega - other.omega,
+        )
+    }
+}
+
+impl<T: Mul<Output = T> + Copy> Mul<T> for Twist<T> {
+    type Output = Self;
+
+    fn mul(self, k: T) -> Self {
+        Self::new(self.vx * k, self.vy * k, self.omega * k)
+    }
+}
+
+impl<T: Neg<Output = T>> Neg for Twist<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.vx, -self.vy, -self.omega)
+    }
+}
+
+impl<T: Num + Copy> VectorSpace for Twist<T> {
+    type Scalar = T;
+
+    fn zero_vector() -> Self {
+        Self::new(zero(), zero(), zero())
+    }
+
+    fn add_vector(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn sub_vector(self, other: Self) -> Self {
+        self - other
+    }
+
+    fn scale(self, k: T) -> Self {
+        self * k
+    }
+
+    fn div_scalar(self, k: T) -> Self {
+        Self::new(self.vx / k, self.vy / k, self.omega / k)
+    }
+}
+
+//
+// Drive kinematics
+//
+
+pub trait DriveKinematics<T> {
+    type Wheels;
+
+    fn forward(&self, wheels: Self::Wheels) -> Twist<T>;
+    fn inverse(&self, twist: Twist<T>) -> Self::Wheels;
+}
+
+// Wheel velocities are (left, right)
+#[derive(Copy, Clone, Debug)]
+pub struct DifferentialDrive<T> {
+    pub track_width: T,
+}
+
+impl<T: Float> DriveKinematics<T> for DifferentialDrive<T> {
+    type Wheels = (T, T);
+
+    fn forward(&self, (left, right): (T, T)) -> Twist<T> {
+        let two = one::<T>() + one();
+        Twist::new(
+            (left + right) / two,
+            zero(),
+            (right - left) / self.track_width,
+        )
+    }
+
+    fn inverse(&self, twist: Twist<T>) -> (T, T) {
+        let two = one::<T>() + one();
+        let turn = twist.omega * self.track_width / two;
+        (twist.vx - turn, twist.vx + turn)
+    }
+}
+
+// Wheel velocities are [front left, front right, rear left, rear right], with
+// rollers at 45 degrees in an X pattern when viewed from above
+#[derive(Copy, Clone, Debug)]
+pub struct MecanumDrive<T> {
+    // Distance from the center to the front axle
+    pub half_length: T,
+    // Distance from the center to the left wheels
+    pub half_width: T,
+}
+
+impl<T: Float> DriveKinematics<T> for MecanumDrive<T> {
+    type Wheels = [T; 4];
+
+    fn forward(&self, [fl, fr, rl, rr]: [T; 4]) -> Twist<T> {
+        let four = T::from(4).unwrap();
+        let k = self.half_length + self.half_width;
+        Twist::new(
+            (fl + fr + rl + rr) / four,
+            (-fl + fr + rl - rr) / four,
+            (-fl + fr - rl + rr) / (four * k),
+        )
+    }
+
+    fn inverse(&self, twist: Twist<T>) -> [T; 4] {
+        let Twist { vx, vy, omega } = twist;
+        let turn = (self.half_length + self.half_width) * omega;
+        [
+            vx - vy - turn,
+            vx + vy + turn,
+            vx + vy - turn,
+            vx - vy + turn,
+        ]
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ModuleState<T> {
+    pub speed: T,
+    pub angle: T,
+}
+
+// Module positions are (x, y) from the center of the robot
+#[derive(Copy, Clone, Debug)]
+pub struct SwerveDrive<T, const N: usize> {
+    pub modules: [(T, T); N],
+}
+
+impl<T: Float, const N: usize> DriveKinematics<T> for SwerveDrive<T, N> {
+    type Wheels = [ModuleState<T>; N];
+
+    // Least squares fit of the twist to the module velocities
+    fn forward(&self, wheels: [ModuleState<T>; N]) -> Twist<T> {
+        let n = T::from(N).unwrap();
+        let mut ata = [[n, zero(), zero()], [zero(), n, zero()], [zero(); 3]];
+        let mut atb = [[zero()]; 3];
+
+        for (&(px, py), module) in self.modules.iter().zip(&wheels) {
+            let (sin, cos) = module.angle.sin_cos();
+            let (sx, sy) = (module.speed * cos, module.speed * sin);
+
+            ata[0][2] = ata[0][2] - py;
+            ata[1][2] = ata[1][2] + px;
+            ata[2][2] = ata[2][2] + px * px + py * py;
+            atb[0][0] = atb[0][0] + sx;
+            atb[1][0] = atb[1][0] + sy;
+            atb[2][0] = atb[2][0] - py * sx + px * sy;
+        }
+        ata[2][0] = ata[0][2];
+        ata[2][1] = ata[1][2];
+
+        let [[vx], [vy], [omega]] = mat_solve(ata, atb);
+        Twist::new(vx, vy, omega)
+    }
+
+    fn inverse(&self, twist: Twist<T>) -> [ModuleState<T>; N] {
+        self.modules.map(|(px, py)| {
+            let sx = twist.vx - twist.omega * py;
+            let sy = twist.vy + twist.omega * px;
+            ModuleState {
+                speed: sx.hypot(sy),
+                angle: sy.atan2(sx),
+            }
+        })
+    }
+}
+
+//
+// Signal functions
+//
+
+pub fn forward_kinematics<Time, T, K>(
+    kinematics: K,
+) -> SF<Time, Lift<Time, K::Wheels, Twist<T>>>
+where
+    K: DriveKinematics<T> + 'static,
+{
+    map(move |wheels| kinematics.forward(wheels))
+}
+
+pub fn inverse_kinematics<Time, T, K>(
+    kinematics: K,
+) -> SF<Time, Lift<Time, Twist<T>, K::Wheels>>
+where
+    K: DriveKinematics<T> + 'static,
+{
+    map(move |twist| kinematics.inverse(twist))
+}
+
+// Integrates a robot-frame twist into a global pose
+pub fn integrate_pose<Time, T>(
+    init: Pose<T>,
+) -> SF<Time, Accum<Time, Twist<T>, Pose<T>>>
+where
+    Time: Into<T>,
+    T: Float + FloatConst,
+{
+    accum(init, |dt: Time, twist, pose: Pose<T>| {
+        pose.integrate(twist, dt.into())
+    })
+}
+
+// Tracks the global pose from wheel velocities
+pub type Odometry<Time, T, Wheels> =
+    Compose<Lift<Time, Wheels, Twist<T>>, Accum<Time, Twist<T>, Pose<T>>>;
+
+pub fn odometry<Time, T, K>(
+    kinematics: K,
+    init: Pose<T>,
+) -> SF<Time, Odometry<Time, T, K::Wheels>>
+where
+    Time: Copy + Into<T>,
+    T: Float + FloatConst,
+    K: DriveKinematics<T> + 'static,
+{
+    forward_kinematics(kinematics) | integrate_pose(init)
+}
@@ -7,6 +7,8 @@ extern crate alloc;
 mod std {
     pub use ::alloc::*;
     pub use ::core::*;
+    // In both, so the globs would make them ambiguous
+    pub use ::alloc::{fmt, slice, str};
 }
 
 #[cfg(feature = "std")]
@@ -18,14 +20,48 @@ extern crate num_traits;
 
 // TODO Make modules public, or re-export everything?
 
+mod arbitrate;
 mod basic;
+mod boxed;
+// Behavior tree names clash with the task combinators, so it isn't re-exported
+pub mod bt;
 mod event;
+mod fluent;
+#[cfg(feature = "std")]
+mod kinematics;
+#[cfg(feature = "std")]
+mod lti;
+mod mission;
 mod operators;
+mod parallel;
+#[cfg(feature = "std")]
+mod path;
+#[cfg(feature = "std")]
+mod profile;
 mod sf;
+mod statechart;
 mod task;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod vector;
 
+pub use arbitrate::*;
 pub use basic::*;
+pub use boxed::*;
 pub use event::*;
+pub use fluent::*;
+#[cfg(feature = "std")]
+pub use kinematics::*;
+#[cfg(feature = "std")]
+pub use lti::*;
+pub use mission::*;
 pub use operators::*;
+pub use parallel::*;
+#[cfg(feature = "std")]
+pub use path::*;
+#[cfg(feature = "std")]
+pub use profile::*;
 pub use sf::*;
+pub use statechart::*;
 pub use task::*;
+pub use vector::*;
@@ -0,0 +1,400 @@
+use crate::std::{marker::PhantomData, vec::Vec};
+
+use num_traits::{one, zero, Float};
+
+use crate::{SignalTrans, VectorSpace, SF};
+
+// Number of Taylor series terms used for the matrix exponential. The series is
+// only evaluated for ||A * dt|| <= 1/2, so this is plenty for f64.
+const EXP_TERMS: usize = 16;
+
+//
+// Matrix helpers
+//
+
+pub type Matrix<T, const R: usize, const C: usize> = [[T; C]; R];
+
+fn mat_zero<T: Float, const R: usize, const C: usize>() -> Matrix<T, R, C> {
+    [[zero(); C]; R]
+}
+
+fn mat_identity<T: Float, const N: usize>() -> Matrix<T, N, N> {
+    let mut m = mat_zero();
+    for (i, row) in m.iter_mut().enumerate() {
+        row[i] = one();
+    }
+    m
+}
+
+fn mat_add<T: Float, const R: usize, const C: usize>(
+    x: &Matrix<T, R, C>,
+    y: &Matrix<T, R, C>,
+) -> Matrix<T, R, C> {
+    let mut m = *x;
+    for (row, y_row) in m.iter_mut().zip(y) {
+        for (v, &y_v) in row.iter_mut().zip(y_row) {
+            *v = *v + y_v;
+        }
+    }
+    m
+}
+
+fn mat_scale<T: Float, const R: usize, const C: usize>(
+    x: &Matrix<T, R, C>,
+    k: T,
+) -> Matrix<T, R, C> {
+    let mut m = *x;
+    for row in m.iter_mut() {
+        for v in row.iter_mut() {
+            *v = *v * k;
+        }
+    }
+    m
+}
+
+fn mat_mul<T: Float, const R: usize, const K: usize, const C: usize>(
+    x: &Matrix<T, R, K>,
+    y: &Matrix<T, K, C>,
+) -> Matrix<T, R, C> {
+    let mut m = mat_zero();
+    for (row, x_row) in m.iter_mut().zip(x) {
+        for (c, v) in row.iter_mut().enumerate() {
+            *v = x_row
+                .iter()
+                .zip(y)
+                .fold(zero(), |sum, (&x_v, y_row)| sum + x_v * y_row[c]);
+        }
+    }
+    m
+}
+
+fn mat_vec<T: Float, const R: usize, const C: usize>(
+    x: &Matrix<T, R, C>,
+    v: &[T; C],
+) -> [T; R] {
+    let mut out = [zero(); R];
+    for (o, row) in out.iter_mut().zip(x) {
+        *o = row
+            .iter()
+            .zip(v)
+            .fold(zero(), |sum, (&x_v, &v_v)| sum + x_v * v_v);
+    }
+    out
+}
+
+fn vec_add<T: Float, const N: usize>(x: &[T; N], y: &[T; N]) -> [T; N] {
+    let mut out = *x;
+    for (o, &y_v) in out.iter_mut().zip(y) {
+        *o = *o + y_v;
+    }
+    out
+}
+
+// Infinity norm (max absolute row sum)
+fn mat_norm<T: Float, const R: usize, const C: usize>(
+    x: &Matrix<T, R, C>,
+) -> T {
+    x.iter()
+        .map(|row| row.iter().fold(zero(), |sum: T, v| sum + v.abs()))
+        .fold(zero(), T::max)
+}
+
+// Computes m^-1 * x using Gauss-Jordan elimination with partial pivoting
+pub(crate) fn mat_solve<T: Float, const N: usize, const C: usize>(
+    mut m: Matrix<T, N, N>,
+    mut x: Matrix<T, N, C>,
+) -> Matrix<T, N, C> {
+    for col in 0..N {
+        let pivot = (col..N)
+            .max_by(|&i, &j| {
+                m[i][col]
+                    .abs()
+                    .partial_cmp(&m[j][col].abs())
+                    .unwrap_or(crate::std::cmp::Ordering::Equal)
+            })
+            .unwrap_or(col);
+        m.swap(col, pivot);
+        x.swap(col, pivot);
+
+        let p = m[col][col];
+        for v in m[col].iter_mut() {
+            *v = *v / p;
+        }
+        for v in x[col].iter_mut() {
+            *v = *v / p;
+        }
+
+        let (m_pivot, x_pivot) = (m[col], x[col]);
+        for (row, (m_row, x_row)) in m.iter_mut().zip(x.iter_mut()).enumerate()
+        {
+            if row != col {
+                let k = m_row[col];
+                for (v, &p) in m_row.iter_mut().zip(&m_pivot) {
+                    *v = *v - k * p;
+                }
+                for (v, &p) in x_row.iter_mut().zip(&x_pivot) {
+                    *v = *v - k * p;
+                }
+            }
+        }
+    }
+    x
+}
+
+//
+// StateSpace
+//
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Discretization {
+    ZeroOrderHold,
+    Tustin,
+}
+
+// Continuous-time system dx/dt = A x + B u, y = C x + D u, discretized with
+// the delta of each step. The output is computed from the state at the end of
+// the step, so a pure integrator matches integral().
+#[derive(Copy, Clone)]
+pub struct StateSpace<Time, T, const N: usize, const M: usize, const P: usize> {
+    a: Matrix<T, N, N>,
+    b: Matrix<T, N, M>,
+    c: Matrix<T, P, N>,
+    d: Matrix<T, P, M>,
+    method: Discretization,
+    state: [T; N],
+    last_input: [T; M],
+    // Discretized A and B, along with the delta they were computed for
+    discrete: Option<(T, Matrix<T, N, N>, Matrix<T, N, M>)>,
+    _time: PhantomData<Time>,
+}
+
+impl<Time, T: Float, const N: usize, const M: usize, const P: usize>
+    StateSpace<Time, T, N, M, P>
+{
+    pub fn new(
+        a: Matrix<T, N, N>,
+        b: Matrix<T, N, M>,
+        c: Matrix<T, P, N>,
+        d: Matrix<T, P, M>,
+        method: Discretization,
+        init: [T; N],
+    ) -> Self {
+        Self {
+            a,
+            b,
+            c,
+            d,
+            method,
+            state: init,
+            last_input: [zero(); M],
+            discrete: None,
+            _time: Default::default(),
+        }
+    }
+
+    pub fn state(&self) -> &[T; N] {
+        &self.state
+    }
+
+    fn discretize(&self, dt: T) -> (Matrix<T, N, N>, Matrix<T, N, M>) {
+        match self.method {
+            Discretization::ZeroOrderHold => {
+                // Scaling and squaring for the exponential and its integral:
+                // Phi(2h) = Phi(h)^2, Gamma(2h) = (I + Phi(h)) Gamma(h)
+                let two: T = one::<T>() + one();
+                let half: T = one::<T>() / two;
+                let mut h = dt;
+                let mut squarings = 0;
+                while mat_norm(&mat_scale(&self.a, h)) > half && squarings < 64
+                {
+                    h = h / two;
+                    squarings += 1;
+                }
+
+                let ah = mat_scale(&self.a, h);
+                let mut term = mat_identity::<T, N>();
+                let mut phi = term;
+                let mut gamma = mat_scale(&term, h);
+                let mut k: T = one();
+                for _ in 1..EXP_TERMS {
+                    term = mat_scale(&mat_mul(&term, &ah), one::<T>() / k);
+                    k = k + one();
+                    phi = mat_add(&phi, &term);
+                    gamma = mat_add(&gamma, &mat_scale(&term, h / k));
+                }
+
+                for _ in 0..squarings {
+                    gamma = mat_mul(&mat_add(&mat_identity(), &phi), &gamma);
+                    phi = mat_mul(&phi, &phi);
+                }
+
+                (phi, mat_mul(&gamma, &self.b))
+            }
+            Discretization::Tustin => {
+                // (I - A dt/2) x[k] = (I + A dt/2) x[k-1]
+                //     + B dt/2 (u[k-1] + u[k])
+                let two: T = one::<T>() + one();
+                let half_a = mat_scale(&self.a, dt / two);
+                let lhs =
+                    mat_add(&mat_identity(), &mat_scale(&half_a, -one::<T>()));
+                (
+                    mat_solve(lhs, mat_add(&mat_identity(), &half_a)),
+                    mat_solve(lhs, mat_scale(&self.b, dt / two)),
+                )
+            }
+        }
+    }
+}
+
+impl<Time, T, const N: usize, const M: usize, const P: usize> SignalTrans<Time>
+    for StateSpace<Time, T, N, M, P>
+where
+    Time: Into<T>,
+    T: Float,
+{
+    type Input = [T; M];
+    type Output = [T; P];
+
+    fn step(self, delta: Time, input: [T; M]) -> (Self, [T; P]) {
+        let dt = delta.into();
+        let (ad, bd) = match self.discrete {
+            Some((last_dt, ad, bd)) if last_dt == dt => (ad, bd),
+            _ => self.discretize(dt),
+        };
+
+        let forcing = match self.method {
+            Discretization::ZeroOrderHold => input,
+            Discretization::Tustin => vec_add(&self.last_input, &input),
+        };
+        let state =
+            vec_add(&mat_vec(&ad, &self.state), &mat_vec(&bd, &forcing));
+        let output =
+            vec_add(&mat_vec(&self.c, &state), &mat_vec(&self.d, &input));
+
+        (
+            Self {
+                state,
+                last_input: input,
+                discrete: Some((dt, ad, bd)),
+                ..self
+            },
+            output,
+        )
+    }
+}
+
+pub fn state_space_init<
+    Time,
+    T,
+    const N: usize,
+    const M: usize,
+    const P: usize,
+>(
+    a: Matrix<T, N, N>,
+    b: Matrix<T, N, M>,
+    c: Matrix<T, P, N>,
+    d: Matrix<T, P, M>,
+    method: Discretization,
+    init: [T; N],
+) -> SF<Time, StateSpace<Time, T, N, M, P>>
+where
+    Time: Into<T>,
+    T: Float,
+{
+    SF::from(StateSpace::new(a, b, c, d, method, init))
+}
+
+pub fn state_space<Time, T, const N: usize, const M: usize, const P: usize>(
+    a: Matrix<T, N, N>,
+    b: Matrix<T, N, M>,
+    c: Matrix<T, P, N>,
+    d: Matrix<T, P, M>,
+    method: Discretization,
+) -> SF<Time, StateSpace<Time, T, N, M, P>>
+where
+    Time: Into<T>,
+    T: Float,
+{
+    state_space_init(a, b, c, d, method, [zero(); N])
+}
+
+//
+// TransferFn
+//
+
+// Discrete transfer function in powers of z^-1:
+// H(z) = (b0 + b1 z^-1 + ...) / (a0 + a1 z^-1 + ...)
+// The sample time is fixed, so delta is ignored. The signal can be any vector
+// space over the coefficients, which filters each component separately.
+#[derive(Clone)]
+pub struct TransferFn<T, V = T> {
+    num: Vec<T>,
+    den: Vec<T>,
+    // Direct form II transposed delay line
+    state: Vec<V>,
+}
+
+impl<T: Float, V: VectorSpace<Scalar = T>> TransferFn<T, V> {
+    pub fn new(mut num: Vec<T>, mut den: Vec<T>) -> Self {
+        assert!(
+            !den.is_empty() && den[0] != zero(),
+            "leading denominator coefficient must be non-zero"
+        );
+
+        let a0 = den[0];
+        let order = num.len().max(den.len());
+        num.resize(order, zero());
+        den.resize(order, zero());
+        for coef in num.iter_mut().chain(den.iter_mut()) {
+            *coef = *coef / a0;
+        }
+
+        Self {
+            num,
+            den,
+            state: crate::std::vec![V::zero_vector(); order - 1],
+        }
+    }
+}
+
+impl<Time, T, V> SignalTrans<Time> for TransferFn<T, V>
+where
+    T: Float,
+    V: VectorSpace<Scalar = T>,
+{
+    type Input = V;
+    type Output = V;
+
+    fn step(mut self, _: Time, input: V) -> (Self, V) {
+        let output = input.clone().scale(self.num[0]).add_vector(
+            self.state.first().cloned().unwrap_or_else(V::zero_vector),
+        );
+
+        let order = self.state.len();
+        for i in 0..order {
+            let next = if i + 1 < order {
+                self.state[i + 1].clone()
+            } else {
+                V::zero_vector()
+            };
+            self.state[i] = input
+                .clone()
+                .scale(self.num[i + 1])
+                .sub_vector(output.clone().scale(self.den[i + 1]))
+                .add_vector(next);
+        }
+
+        (self, output)
+    }
+}
+
+pub fn transfer_fn<Time, T, V>(
+    num: Vec<T>,
+    den: Vec<T>,
+) -> SF<Time, TransferFn<T, V>>
+where
+    T: Float,
+    V: VectorSpace<Scalar = T>,
+{
+    SF::from(TransferFn::new(num, den))
+}
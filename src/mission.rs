@@ -0,0 +1,513 @@
+// Text format for missions, compiled into tasks at runtime. A script is a list
+// of statements separated by semicolons or newlines, each a verb followed by
+// numbers and words, and # starts a comment:
+//
+//     drive_to 1.0 2.0; wait 3
+//     timeout 10 { grab; lift 0.5 }
+//
+// Verbs and conditions are looked up in a MissionRegistry, apart from these
+// built in ones which take a block of statements:
+//
+//     timeout <seconds> { ... }    Stops the block after the time limit
+//     until <condition> { ... }    Stops the block when the condition fires
+//     repeat <times> { ... }       Runs the block the given number of times
+
+use crate::std::{
+    collections::BTreeMap,
+    fmt, format,
+    iter::Peekable,
+    rc::Rc,
+    str::Chars,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use num_traits::{FromPrimitive, Num, Signed};
+
+use crate::{
+    after, boxed, map, repeat, sequence_vec, stop_with, BoxedSF, Events,
+    Occurrences, SFExt, SignalTrans, TaskOutput, SF,
+};
+
+// Every mission step ends with () so that they can be sequenced. The output is
+// None on steps with nothing to output, e.g. while a repeated block waits to
+// restart.
+pub type MissionTask<Time, A, Out> =
+    BoxedSF<Time, A, TaskOutput<Option<Out>, ()>>;
+
+// Conditions can have any kind of Occurrences, which are collected into Events
+pub type MissionCondition<Time, A> = BoxedSF<Time, A, Events<Time, ()>>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Arg {
+    Number(f64),
+    Word(String),
+}
+
+// Arguments of a statement, with accessors which make error messages
+#[derive(Copy, Clone, Debug)]
+pub struct Args<'a>(&'a [Arg]);
+
+impl<'a> Args<'a> {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Option<&'a Arg> {
+        self.0.get(i)
+    }
+
+    pub fn number(&self, i: usize) -> Result<f64, String> {
+        match self.0.get(i) {
+            Some(Arg::Number(n)) => Ok(*n),
+            Some(Arg::Word(_)) => {
+                Err(format!("argument {} must be a number", i + 1))
+            }
+            None => Err(format!("missing argument {}", i + 1)),
+        }
+    }
+
+    pub fn word(&self, i: usize) -> Result<&'a str, String> {
+        match self.0.get(i) {
+            Some(Arg::Word(w)) => Ok(w),
+            Some(Arg::Number(_)) => {
+                Err(format!("argument {} must be a word", i + 1))
+            }
+            None => Err(format!("missing argument {}", i + 1)),
+        }
+    }
+
+    // Fails unless there are exactly count arguments
+    pub fn expect_len(&self, count: usize) -> Result<(), String> {
+        if self.0.len() == count {
+            Ok(())
+        } else {
+            Err(format!(
+                "expected {} arguments, found {}",
+                count,
+                self.0.len()
+            ))
+        }
+    }
+}
+
+// Lines and columns start at 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MissionError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for MissionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MissionError {}
+
+//
+// Parser
+//
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Arg(Arg),
+    Open,
+    Close,
+    Separator,
+    End,
+}
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            chars: source.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(
+        &self,
+        line: usize,
+        column: usize,
+        message: String,
+    ) -> MissionError {
+        MissionError {
+            line,
+            column,
+            message,
+        }
+    }
+
+    // Next token along with where it starts
+    fn next(&mut self) -> Result<(Token, usize, usize), MissionError> {
+        loop {
+            match self.chars.peek() {
+                Some('#') => {
+                    while !matches!(self.chars.peek(), None | Some('\n')) {
+                        self.bump();
+                    }
+                }
+                Some(c) if c.is_whitespace() && *c != '\n' => {
+                    self.bump();
+                }
+                _ => break,
+            }
+        }
+
+        let (line, column) = (self.line, self.column);
+        let c = match self.bump() {
+            Some(c) => c,
+            None => return Ok((Token::End, line, column)),
+        };
+        let token = match c {
+            '{' => Token::Open,
+            '}' => Token::Close,
+            ';' | '\n' => Token::Separator,
+            c if c.is_alphanumeric() || "_-+.".contains(c) => {
+                let mut text = String::new();
+                text.push(c);
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_alphanumeric() || "_-+.".contains(c) {
+                        text.push(c);
+                        self.bump();
+                    } else {
+                        break;
+                    }
+                }
+
+                // Only parse numbers from digits and signs, so that words
+                // like inf and nan stay words
+                if !c.is_ascii_digit() && !"-+.".contains(c) {
+                    Token::Arg(Arg::Word(text))
+                } else {
+                    match text.parse::<f64>() {
+                        Ok(n) if n.is_finite() => Token::Arg(Arg::Number(n)),
+                        Ok(_) => {
+                            let message =
+                                format!("number `{}` isn't finite", text);
+                            return Err(self.error(line, column, message));
+                        }
+                        Err(_) => {
+                            let message = format!("invalid number `{}`", text);
+                            return Err(self.error(line, column, message));
+                        }
+                    }
+                }
+            }
+            c => {
+                let message = format!("unexpected character `{}`", c);
+                return Err(self.error(line, column, message));
+            }
+        };
+        Ok((token, line, column))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Statement {
+    verb: String,
+    args: Vec<Arg>,
+    block: Option<Vec<Statement>>,
+    line: usize,
+    column: usize,
+}
+
+impl Statement {
+    fn error(&self, message: String) -> MissionError {
+        MissionError {
+            line: self.line,
+            column: self.column,
+            message,
+        }
+    }
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    peeked: Option<(Token, usize, usize)>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Result<&Token, MissionError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lexer.next()?);
+        }
+        Ok(&self.peeked.as_ref().unwrap().0)
+    }
+
+    fn next(&mut self) -> Result<(Token, usize, usize), MissionError> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.lexer.next(),
+        }
+    }
+
+    // Statements up to the end, or a closing brace if in a block opened at
+    // the given line and column
+    fn statements(
+        &mut self,
+        open: Option<(usize, usize)>,
+    ) -> Result<Vec<Statement>, MissionError> {
+        let mut statements = Vec::new();
+        loop {
+            let (token, line, column) = self.next()?;
+            let verb = match token {
+                Token::Separator => continue,
+                Token::Close if open.is_some() => return Ok(statements),
+                Token::End => match open {
+                    None => return Ok(statements),
+                    Some((line, column)) => {
+                        return Err(MissionError {
+                            line,
+                            column,
+                            message: "unclosed `{`".to_string(),
+                        });
+                    }
+                },
+                Token::Arg(Arg::Word(verb)) => verb,
+                token => {
+                    let message = match token {
+                        Token::Arg(Arg::Number(_)) => {
+                            "expected a verb, found a number"
+                        }
+                        Token::Open => "expected a verb, found `{`",
+                        _ => "unmatched `}`",
+                    };
+                    return Err(MissionError {
+                        line,
+                        column,
+                        message: message.to_string(),
+                    });
+                }
+            };
+
+            let mut args = Vec::new();
+            while let Token::Arg(_) = self.peek()? {
+                if let (Token::Arg(arg), ..) = self.next()? {
+                    args.push(arg);
+                }
+            }
+
+            let block = if *self.peek()? == Token::Open {
+                let (_, line, column) = self.next()?;
+                Some(self.statements(Some((line, column)))?)
+            } else {
+                None
+            };
+
+            statements.push(Statement {
+                verb,
+                args,
+                block,
+                line,
+                column,
+            });
+        }
+    }
+}
+
+//
+// MissionRegistry
+//
+
+type VerbFn<Time, A, Out> =
+    Rc<dyn Fn(Args) -> Result<MissionTask<Time, A, Out>, String>>;
+
+type ConditionFn<Time, A> =
+    Rc<dyn Fn(Args) -> Result<MissionCondition<Time, A>, String>>;
+
+// Maps verbs and conditions used in scripts to functions which build them
+#[derive(Clone)]
+pub struct MissionRegistry<Time, A, Out> {
+    verbs: BTreeMap<String, VerbFn<Time, A, Out>>,
+    conditions: BTreeMap<String, ConditionFn<Time, A>>,
+}
+
+impl<Time, A, Out> Default for MissionRegistry<Time, A, Out> {
+    fn default() -> Self {
+        Self {
+            verbs: BTreeMap::new(),
+            conditions: BTreeMap::new(),
+        }
+    }
+}
+
+impl<Time, A, Out> MissionRegistry<Time, A, Out>
+where
+    Time: Num + Signed + PartialOrd + FromPrimitive + Copy + 'static,
+    A: Clone + 'static,
+    Out: 'static,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Errors returned by f are reported at the statement using the verb
+    pub fn verb<S, F>(&mut self, name: &str, f: F) -> &mut Self
+    where
+        S: SignalTrans<Time, Input = A, Output = TaskOutput<Out, ()>>
+            + Clone
+            + 'static,
+        F: Fn(Args) -> Result<SF<Time, S>, String> + 'static,
+    {
+        let f = move |args: Args| {
+            f(args).map(|task| {
+                let task = task
+                    | map(|val: TaskOutput<Out, ()>| val.map_running(Some));
+                BoxedSF::new(task.into_inner())
+            })
+        };
+        self.verbs.insert(name.to_string(), Rc::new(f));
+        self
+    }
+
+    pub fn condition<S, F>(&mut self, name: &str, f: F) -> &mut Self
+    where
+        S: SignalTrans<Time, Input = A> + Clone + 'static,
+        S::Output: Occurrences<Time, Value = ()>,
+        F: Fn(Args) -> Result<SF<Time, S>, String> + 'static,
+    {
+        let f = move |args: Args| {
+            f(args).map(|cond| {
+                let cond = cond.map(|occurrences: S::Output| {
+                    let mut events = Events::new();
+                    for (age, ()) in occurrences.occurrences() {
+                        events.push(age, ());
+                    }
+                    events
+                });
+                BoxedSF::new(cond.into_inner())
+            })
+        };
+        self.conditions.insert(name.to_string(), Rc::new(f));
+        self
+    }
+
+    pub fn compile(
+        &self,
+        script: &str,
+    ) -> Result<SF<Time, MissionTask<Time, A, Out>>, MissionError> {
+        let mut parser = Parser {
+            lexer: Lexer::new(script),
+            peeked: None,
+        };
+        let statements = parser.statements(None)?;
+        self.compile_block(&statements)
+    }
+
+    fn compile_block(
+        &self,
+        statements: &[Statement],
+    ) -> Result<SF<Time, MissionTask<Time, A, Out>>, MissionError> {
+        let tasks = statements
+            .iter()
+            .map(|statement| self.compile_statement(statement))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(boxed(
+            sequence_vec(tasks)
+                | map(|val: TaskOutput<Option<Out>, Vec<()>>| {
+                    val.map_stopped(|_| ())
+                }),
+        ))
+    }
+
+    fn compile_statement(
+        &self,
+        statement: &Statement,
+    ) -> Result<SF<Time, MissionTask<Time, A, Out>>, MissionError> {
+        let args = Args(&statement.args);
+        match statement.verb.as_str() {
+            "timeout" | "until" | "repeat" => {
+                let block = statement.block.as_ref().ok_or_else(|| {
+                    statement
+                        .error(format!("`{}` needs a block", statement.verb))
+                })?;
+                let block = self.compile_block(block)?;
+                self.compile_builtin(&statement.verb, args, block)
+                    .map_err(|message| statement.error(message))
+            }
+            verb => {
+                if statement.block.is_some() {
+                    let message = format!("`{}` doesn't take a block", verb);
+                    return Err(statement.error(message));
+                }
+                let f = self.verbs.get(verb).ok_or_else(|| {
+                    statement.error(format!("unknown verb `{}`", verb))
+                })?;
+                f(args)
+                    .map(SF::from)
+                    .map_err(|message| statement.error(message))
+            }
+        }
+    }
+
+    fn compile_builtin(
+        &self,
+        verb: &str,
+        args: Args,
+        block: SF<Time, MissionTask<Time, A, Out>>,
+    ) -> Result<SF<Time, MissionTask<Time, A, Out>>, String> {
+        match verb {
+            "timeout" => {
+                args.expect_len(1)?;
+                let limit = args.number(0)?;
+                if limit < 0.0 {
+                    return Err("time limit can't be negative".to_string());
+                }
+                let limit = Time::from_f64(limit)
+                    .ok_or_else(|| "time limit out of range".to_string())?;
+                Ok(boxed(stop_with(block, after(limit, ()))))
+            }
+            "until" => {
+                let name = args.word(0)?;
+                let f = self
+                    .conditions
+                    .get(name)
+                    .ok_or_else(|| format!("unknown condition `{}`", name))?;
+                let cond = f(Args(&args.0[1..]))?;
+                Ok(boxed(stop_with(block, SF::from(cond))))
+            }
+            _ => {
+                args.expect_len(1)?;
+                let times = args.number(0)?;
+                if times < 1.0 || times as usize as f64 != times {
+                    let message = "repeat count must be a positive integer";
+                    return Err(message.to_string());
+                }
+                Ok(boxed(
+                    repeat(times as usize, block)
+                        | map(|val: TaskOutput<Option<Option<Out>>, ()>| {
+                            val.map_running(Option::flatten)
+                        }),
+                ))
+            }
+        }
+    }
+}
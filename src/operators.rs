@@ -1,12 +1,31 @@
-use crate::std::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Sub};
-
-use num_traits::{zero, Num};
+use crate::std::ops::{
+    Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Not, Rem, Sub,
+};
 
 use crate::{
-    accum, compose, delay, fanout, identity, lift, map2, split, Compose,
-    Fanout, Lift, SignalTrans, Split, SF,
+    accum, compose, delay, fanout, identity, lift, map, map2, split, Accum,
+    Compose, Delay, Fanout, Identity, Lift, SignalTrans, Split, VectorSpace,
+    SF,
 };
 
+// SF whose output is passed through a function
+pub type MapOutput<Time, T, C> =
+    Compose<T, Lift<Time, <T as SignalTrans<Time>>::Output, C>>;
+
+// SF whose output is the outputs of two SFs, run on the same input, combined
+// by a function
+pub type Combine<Time, T, U, C> = Compose<
+    Fanout<Time, T, U>,
+    Lift<
+        Time,
+        (
+            <T as SignalTrans<Time>>::Output,
+            <U as SignalTrans<Time>>::Output,
+        ),
+        C,
+    >,
+>;
+
 impl<Time: Copy, T, U> BitOr<SF<Time, U>> for SF<Time, T>
 where
     T: SignalTrans<Time>,
@@ -53,16 +72,9 @@ macro_rules! overload_operator {
             T::Input: Clone,
             T::Output: $optrait<U::Output>,
         {
-            // TODO File bug report for impl ... working here
-            type Output = SF<Time,
-                Compose<
-                    Fanout<Time, T, U>,
-                    Lift<
-                        Time,
-                        (T::Output, U::Output),
-                        <T::Output as $optrait<U::Output>>::Output,
-                    >,
-                >
+            type Output = SF<
+                Time,
+                Combine<Time, T, U, <T::Output as $optrait<U::Output>>::Output>,
             >;
 
             fn $opfunc(self, other: SF<Time, U>) -> Self::Output {
@@ -76,22 +88,199 @@ overload_operator!(Add, add, +);
 overload_operator!(Sub, sub, -);
 overload_operator!(Mul, mul, *);
 overload_operator!(Div, div, /);
+overload_operator!(Rem, rem, %);
+
+// Operators between an SF's output and a constant, on either side. These have
+// to be implemented for each scalar type since a blanket impl would overlap
+// with the ones between SFs.
+macro_rules! overload_scalar_operator {
+    ($optrait:ident, $opfunc:ident, $op:tt, $($scalar:ty),+) => {
+        $(
+            impl<Time: Copy, T> $optrait<$scalar> for SF<Time, T>
+            where
+                T: SignalTrans<Time>,
+                T::Output: $optrait<$scalar>,
+            {
+                type Output = SF<
+                    Time,
+                    MapOutput<
+                        Time,
+                        T,
+                        <T::Output as $optrait<$scalar>>::Output,
+                    >,
+                >;
+
+                fn $opfunc(self, k: $scalar) -> Self::Output {
+                    compose(self, map(move |x| x $op k))
+                }
+            }
+
+            impl<Time: Copy, T> $optrait<SF<Time, T>> for $scalar
+            where
+                T: SignalTrans<Time>,
+                $scalar: $optrait<T::Output>,
+            {
+                type Output = SF<
+                    Time,
+                    MapOutput<
+                        Time,
+                        T,
+                        <$scalar as $optrait<T::Output>>::Output,
+                    >,
+                >;
 
-pub fn derivative<Time, T>(
-) -> SF<Time, impl SignalTrans<Time, Input = T, Output = T>>
+                fn $opfunc(self, sf: SF<Time, T>) -> Self::Output {
+                    compose(sf, map(move |x| self $op x))
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! overload_scalar_operators {
+    ($($scalar:ty),+) => {
+        overload_scalar_operator!(Add, add, +, $($scalar),+);
+        overload_scalar_operator!(Sub, sub, -, $($scalar),+);
+        overload_scalar_operator!(Mul, mul, *, $($scalar),+);
+        overload_scalar_operator!(Div, div, /, $($scalar),+);
+        overload_scalar_operator!(Rem, rem, %, $($scalar),+);
+    }
+}
+
+overload_scalar_operators!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64
+);
+
+impl<Time: Copy, T> Neg for SF<Time, T>
 where
-    Time: Num + Copy + Into<T>,
-    T: Num + Clone,
+    T: SignalTrans<Time>,
+    T::Output: Neg,
 {
-    (identity::<Time, T>() - delay::<Time, T>(zero()))
-        | lift(|dt: Time, df| df / dt.into())
+    type Output = SF<Time, MapOutput<Time, T, <T::Output as Neg>::Output>>;
+
+    fn neg(self) -> Self::Output {
+        compose(self, map(|x: T::Output| -x))
+    }
 }
 
-pub fn integral<Time, T>(
-) -> SF<Time, impl SignalTrans<Time, Input = T, Output = T>>
+// Mostly for bool signals
+impl<Time: Copy, T> Not for SF<Time, T>
+where
+    T: SignalTrans<Time>,
+    T::Output: Not,
+{
+    type Output = SF<Time, MapOutput<Time, T, <T::Output as Not>::Output>>;
+
+    fn not(self) -> Self::Output {
+        compose(self, map(|x: T::Output| !x))
+    }
+}
+
+//
+// Comparisons
+//
+
+// Comparisons of an SF's output with another SF's or with a constant, giving
+// bool signals (e.g. to feed edge()). The operators can't be overloaded for
+// these since PartialOrd has to return a bool.
+macro_rules! comparison {
+    ($func:ident, $func_const:ident, $op:tt) => {
+        pub fn $func<U>(
+            self,
+            other: SF<Time, U>,
+        ) -> SF<Time, Combine<Time, T, U, bool>>
+        where
+            U: SignalTrans<Time, Input = T::Input>,
+            T::Input: Clone,
+            T::Output: PartialOrd<U::Output>,
+        {
+            (self & other) | map2(|x, y| x $op y)
+        }
+
+        pub fn $func_const<K>(self, k: K) -> SF<Time, MapOutput<Time, T, bool>>
+        where
+            T::Output: PartialOrd<K>,
+            K: 'static,
+        {
+            compose(self, map(move |x| x $op k))
+        }
+    }
+}
+
+impl<Time: Copy, T: SignalTrans<Time>> SF<Time, T> {
+    comparison!(gt, gt_const, >);
+    comparison!(ge, ge_const, >=);
+    comparison!(lt, lt_const, <);
+    comparison!(le, le_const, <=);
+
+    pub fn eq<U>(
+        self,
+        other: SF<Time, U>,
+    ) -> SF<Time, Combine<Time, T, U, bool>>
+    where
+        U: SignalTrans<Time, Input = T::Input>,
+        T::Input: Clone,
+        T::Output: PartialEq<U::Output>,
+    {
+        (self & other) | map2(|x, y| x == y)
+    }
+
+    pub fn eq_const<K>(self, k: K) -> SF<Time, MapOutput<Time, T, bool>>
+    where
+        T::Output: PartialEq<K>,
+        K: 'static,
+    {
+        self | map(move |x| x == k)
+    }
+
+    // Whether both bool signals are true (use ! for negation). Unlike &&,
+    // both SFs are always stepped.
+    pub fn and<U>(
+        self,
+        other: SF<Time, U>,
+    ) -> SF<Time, Combine<Time, T, U, bool>>
+    where
+        U: SignalTrans<Time, Input = T::Input, Output = bool>,
+        T: SignalTrans<Time, Output = bool>,
+        T::Input: Clone,
+    {
+        (self & other) | map2(|x, y| x && y)
+    }
+
+    pub fn or<U>(
+        self,
+        other: SF<Time, U>,
+    ) -> SF<Time, Combine<Time, T, U, bool>>
+    where
+        U: SignalTrans<Time, Input = T::Input, Output = bool>,
+        T: SignalTrans<Time, Output = bool>,
+        T::Input: Clone,
+    {
+        (self & other) | map2(|x, y| x || y)
+    }
+}
+
+pub type Derivative<Time, T> = Combine<Time, Identity<T>, Delay<T>, T>;
+
+pub fn derivative<Time, T>() -> SF<Time, Derivative<Time, T>>
+where
+    Time: Copy + Into<T::Scalar>,
+    T: VectorSpace,
+{
+    (identity::<Time, T>() & delay::<Time, T>(T::zero_vector()))
+        | lift(|dt: Time, (x, last): (T, T)| {
+            x.sub_vector(last).div_scalar(dt.into())
+        })
+}
+
+pub type Integral<Time, T> = Accum<Time, T, T>;
+
+pub fn integral<Time, T>() -> SF<Time, Integral<Time, T>>
 where
-    T: Num + Clone,
-    Time: Num + Into<T>,
+    T: VectorSpace,
+    Time: Into<T::Scalar>,
 {
-    accum(zero(), |dt: Time, x, sum| sum + x * dt.into())
+    accum(T::zero_vector(), |dt: Time, x: T, sum: T| {
+        sum.add_vector(x.scale(dt.into()))
+    })
 }
@@ -0,0 +1,321 @@
+use crate::std::{rc::Rc, vec::Vec};
+
+use crate::{SignalTrans, TaskOutput, TaskState, SF};
+
+// Smallest leftover of a group of stopped tasks, which is the leftover of the
+// one that stopped last
+fn min_leftover<Time, I>(leftovers: I) -> Option<Time>
+where
+    Time: PartialOrd,
+    I: IntoIterator<Item = Option<Time>>,
+{
+    let mut leftovers = leftovers.into_iter();
+    let first = leftovers.next()??;
+    leftovers.try_fold(first, |min, leftover| {
+        let leftover = leftover?;
+        Some(if leftover < min { leftover } else { min })
+    })
+}
+
+//
+// Race
+//
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RaceEnd<A, B> {
+    First(A),
+    Second(B),
+}
+
+// Runs two tasks at the same time, stopping with the end value of whichever
+// stops first. The first task wins if both stop on the same step.
+#[derive(Copy, Clone)]
+pub enum Race<Time, S, T, EndA, EndB> {
+    Running(S, T),
+    Stopped(RaceEnd<EndA, EndB>, Option<Time>),
+}
+
+impl<Time, S, T, OutA, OutB, EndA, EndB> SignalTrans<Time>
+    for Race<Time, S, T, EndA, EndB>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<OutA, EndA>>,
+    T: SignalTrans<Time, Input = S::Input, Output = TaskOutput<OutB, EndB>>,
+    S::Input: Clone,
+    EndA: Clone,
+    EndB: Clone,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<(OutA, OutB), RaceEnd<EndA, EndB>>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        match self {
+            Race::Running(first, second) => {
+                let (first_next, first_val) = first.step(delta, input.clone());
+                let (second_next, second_val) = second.step(delta, input);
+                let (end, leftover) = match (first_val, second_val) {
+                    (TaskOutput::Running(a), TaskOutput::Running(b)) => {
+                        return (
+                            Race::Running(first_next, second_next),
+                            TaskOutput::Running((a, b)),
+                        );
+                    }
+                    (TaskOutput::Stopped(end), _) => {
+                        (RaceEnd::First(end), first_next.leftover())
+                    }
+                    (_, TaskOutput::Stopped(end)) => {
+                        (RaceEnd::Second(end), second_next.leftover())
+                    }
+                };
+                (
+                    Race::Stopped(end.clone(), leftover),
+                    TaskOutput::Stopped(end),
+                )
+            }
+            Race::Stopped(end, _) => (
+                Race::Stopped(end.clone(), Some(delta)),
+                TaskOutput::Stopped(end),
+            ),
+        }
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        match self {
+            Race::Running(..) => None,
+            Race::Stopped(_, leftover) => *leftover,
+        }
+    }
+}
+
+pub fn race<Time, S, T, OutA, OutB, EndA, EndB>(
+    first: SF<Time, S>,
+    second: SF<Time, T>,
+) -> SF<Time, Race<Time, S, T, EndA, EndB>>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<OutA, EndA>>,
+    T: SignalTrans<Time, Input = S::Input, Output = TaskOutput<OutB, EndB>>,
+    S::Input: Clone,
+    EndA: Clone,
+    EndB: Clone,
+{
+    SF::from(Race::Running(first.into_inner(), second.into_inner()))
+}
+
+//
+// Join
+//
+
+// Runs two tasks at the same time, stopping with both end values once both
+// have stopped. The output of a task is None once it has stopped.
+#[derive(Copy, Clone)]
+pub struct Join<Time, S, T, EndA, EndB> {
+    first: TaskState<Time, S, EndA>,
+    second: TaskState<Time, T, EndB>,
+}
+
+impl<Time, S, T, OutA, OutB, EndA, EndB> SignalTrans<Time>
+    for Join<Time, S, T, EndA, EndB>
+where
+    Time: Copy + PartialOrd,
+    S: SignalTrans<Time, Output = TaskOutput<OutA, EndA>>,
+    T: SignalTrans<Time, Input = S::Input, Output = TaskOutput<OutB, EndB>>,
+    S::Input: Clone,
+    EndA: Clone,
+    EndB: Clone,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<(Option<OutA>, Option<OutB>), (EndA, EndB)>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let (first, first_val) = self.first.step(delta, input.clone());
+        let (second, second_val) = self.second.step(delta, input);
+        let output = match (first_val, second_val) {
+            (TaskOutput::Stopped(a), TaskOutput::Stopped(b)) => {
+                TaskOutput::Stopped((a, b))
+            }
+            (first_val, second_val) => {
+                TaskOutput::Running((first_val.running(), second_val.running()))
+            }
+        };
+        (Join { first, second }, output)
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        min_leftover([self.first.leftover(), self.second.leftover()])
+    }
+}
+
+pub fn join<Time, S, T, OutA, OutB, EndA, EndB>(
+    first: SF<Time, S>,
+    second: SF<Time, T>,
+) -> SF<Time, Join<Time, S, T, EndA, EndB>>
+where
+    Time: Copy + PartialOrd,
+    S: SignalTrans<Time, Output = TaskOutput<OutA, EndA>>,
+    T: SignalTrans<Time, Input = S::Input, Output = TaskOutput<OutB, EndB>>,
+    S::Input: Clone,
+    EndA: Clone,
+    EndB: Clone,
+{
+    SF::from(Join {
+        first: TaskState::new(first.into_inner()),
+        second: TaskState::new(second.into_inner()),
+    })
+}
+
+//
+// JoinAll
+//
+
+// Runs any number of tasks at the same time, stopping with all of their end
+// values once every task has stopped. While running, the outputs (None for
+// tasks which have stopped) are combined into one. Use boxed() to join
+// different types of tasks.
+#[derive(Clone)]
+pub struct JoinAll<Time, S, Out, End, C> {
+    tasks: Vec<TaskState<Time, S, End>>,
+    combine: Rc<dyn Fn(Vec<Option<Out>>) -> C>,
+}
+
+impl<Time, S, Out, End, C> SignalTrans<Time> for JoinAll<Time, S, Out, End, C>
+where
+    Time: Copy + PartialOrd,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
+    S::Input: Clone,
+    End: Clone,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<C, Vec<End>>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let JoinAll { tasks, combine } = self;
+
+        let (tasks, vals): (Vec<_>, Vec<_>) = tasks
+            .into_iter()
+            .map(|task| task.step(delta, input.clone()))
+            .unzip();
+
+        let output = if vals.iter().any(TaskOutput::is_running) {
+            let running = vals.into_iter().map(TaskOutput::running).collect();
+            TaskOutput::Running(combine(running))
+        } else {
+            TaskOutput::Stopped(
+                vals.into_iter().filter_map(TaskOutput::stopped).collect(),
+            )
+        };
+        (JoinAll { tasks, combine }, output)
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        min_leftover(self.tasks.iter().map(TaskState::leftover))
+    }
+}
+
+pub fn join_all<Time, S, Out, End, C, F>(
+    tasks: Vec<SF<Time, S>>,
+    combine: F,
+) -> SF<Time, JoinAll<Time, S, Out, End, C>>
+where
+    Time: Copy + PartialOrd,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
+    S::Input: Clone,
+    End: Clone,
+    F: Fn(Vec<Option<Out>>) -> C + 'static,
+{
+    SF::from(JoinAll {
+        tasks: tasks
+            .into_iter()
+            .map(|task| TaskState::new(task.into_inner()))
+            .collect(),
+        combine: Rc::new(combine),
+    })
+}
+
+//
+// JoinAny
+//
+
+// Runs any number of tasks at the same time, stopping with the index and end
+// value of the first one to stop (the lowest index if several stop on the same
+// step). While running, the outputs are combined into one.
+#[derive(Clone)]
+pub enum JoinAny<Time, S, Out, End, C> {
+    Running {
+        tasks: Vec<S>,
+        combine: Rc<dyn Fn(Vec<Out>) -> C>,
+    },
+    Stopped((usize, End), Option<Time>),
+}
+
+impl<Time, S, Out, End, C> SignalTrans<Time> for JoinAny<Time, S, Out, End, C>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
+    S::Input: Clone,
+    End: Clone,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<C, (usize, End)>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        match self {
+            JoinAny::Running { tasks, combine } => {
+                let mut tasks_next = Vec::with_capacity(tasks.len());
+                let mut running = Vec::with_capacity(tasks.len());
+                for (i, task) in tasks.into_iter().enumerate() {
+                    let (task_next, val) = task.step(delta, input.clone());
+                    match val {
+                        TaskOutput::Running(val) => running.push(val),
+                        TaskOutput::Stopped(end) => {
+                            let leftover = task_next.leftover();
+                            return (
+                                JoinAny::Stopped((i, end.clone()), leftover),
+                                TaskOutput::Stopped((i, end)),
+                            );
+                        }
+                    }
+                    tasks_next.push(task_next);
+                }
+                let output = TaskOutput::Running(combine(running));
+                (
+                    JoinAny::Running {
+                        tasks: tasks_next,
+                        combine,
+                    },
+                    output,
+                )
+            }
+            JoinAny::Stopped(end, _) => (
+                JoinAny::Stopped(end.clone(), Some(delta)),
+                TaskOutput::Stopped(end),
+            ),
+        }
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        match self {
+            JoinAny::Running { .. } => None,
+            JoinAny::Stopped(_, leftover) => *leftover,
+        }
+    }
+}
+
+pub fn join_any<Time, S, Out, End, C, F>(
+    tasks: Vec<SF<Time, S>>,
+    combine: F,
+) -> SF<Time, JoinAny<Time, S, Out, End, C>>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
+    S::Input: Clone,
+    End: Clone,
+    F: Fn(Vec<Out>) -> C + 'static,
+{
+    assert!(!tasks.is_empty(), "join_any needs at least one task");
+
+    SF::from(JoinAny::Running {
+        tasks: tasks.into_iter().map(SF::into_inner).collect(),
+        combine: Rc::new(combine),
+    })
+}
@@ -0,0 +1,333 @@
+use crate::std::{marker::PhantomData, vec::Vec};
+
+use num_traits::{one, zero, Float, FloatConst, NumCast};
+
+use crate::{normalize_angle, Pose, SignalTrans, TaskOutput, Twist, SF};
+
+//
+// PurePursuit
+//
+
+#[derive(Copy, Clone, Debug)]
+pub struct PurePursuitParams<T> {
+    pub lookahead: T,
+    pub speed: T,
+    // Distance from the end of the path at which the task stops
+    pub tolerance: T,
+}
+
+// Steers towards a point on the path one lookahead distance away, stopping
+// with the current pose once the end of the path is reached
+#[derive(Clone)]
+pub enum PurePursuit<Time, T> {
+    Running {
+        path: Vec<(T, T)>,
+        params: PurePursuitParams<T>,
+        // Segment index and fraction along it of the last lookahead point
+        progress: (usize, T),
+        _time: PhantomData<Time>,
+    },
+    Stopped(Pose<T>),
+}
+
+impl<Time, T: Float + FloatConst> PurePursuit<Time, T> {
+    pub fn new(path: Vec<(T, T)>, params: PurePursuitParams<T>) -> Self {
+        assert!(!path.is_empty(), "path must have at least one point");
+
+        PurePursuit::Running {
+            path,
+            params,
+            progress: (0, zero()),
+            _time: Default::default(),
+        }
+    }
+}
+
+// Intersection of a segment with a circle around the origin, as a fraction
+// along the segment. Of the two intersections, the later one is taken.
+fn circle_intersection<T: Float>(
+    (x1, y1): (T, T),
+    (x2, y2): (T, T),
+    radius: T,
+) -> Option<T> {
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let a = dx * dx + dy * dy;
+    let b = (x1 * dx + y1 * dy) * (one::<T>() + one());
+    let c = x1 * x1 + y1 * y1 - radius * radius;
+    let four = T::from(4).unwrap();
+    let discriminant = b * b - four * a * c;
+    if a == zero() || discriminant < zero() {
+        return None;
+    }
+
+    let t = (-b + discriminant.sqrt()) / (a + a);
+    if t >= zero() && t <= one() {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+fn lerp_point<T: Float>((x1, y1): (T, T), (x2, y2): (T, T), t: T) -> (T, T) {
+    (x1 + (x2 - x1) * t, y1 + (y2 - y1) * t)
+}
+
+impl<Time, T: Float + FloatConst> SignalTrans<Time> for PurePursuit<Time, T> {
+    type Input = Pose<T>;
+    type Output = TaskOutput<Twist<T>, Pose<T>>;
+
+    fn step(self, _: Time, pose: Pose<T>) -> (Self, Self::Output) {
+        match self {
+            PurePursuit::Running {
+                path,
+                params,
+                progress,
+                ..
+            } => {
+                let end = path[path.len() - 1];
+                let (end_x, end_y) = pose.to_local(end.0, end.1);
+                let end_dist = end_x.hypot(end_y);
+
+                // Follow the end point directly once it is within the
+                // lookahead distance, otherwise take the intersection with
+                // the first segment, starting from the last lookahead point,
+                // which crosses the lookahead circle ahead of it
+                let (progress, goal) = if end_dist <= params.lookahead {
+                    if end_dist <= params.tolerance || end_x < zero() {
+                        return (
+                            PurePursuit::Stopped(pose),
+                            TaskOutput::Stopped(pose),
+                        );
+                    }
+                    ((path.len() - 1, zero()), (end_x, end_y))
+                } else {
+                    let local: Vec<_> = path
+                        .iter()
+                        .map(|&(x, y)| pose.to_local(x, y))
+                        .collect();
+                    local
+                        .windows(2)
+                        .enumerate()
+                        .skip(progress.0)
+                        .find_map(|(i, seg)| {
+                            circle_intersection(
+                                seg[0],
+                                seg[1],
+                                params.lookahead,
+                            )
+                            .filter(|&t| (i, t) >= progress)
+                            .map(|t| ((i, t), lerp_point(seg[0], seg[1], t)))
+                        })
+                        .unwrap_or_else(|| {
+                            // Lost the path, so head back to the last
+                            // lookahead point
+                            let (i, t) = progress;
+                            let next = local.get(i + 1).unwrap_or(&local[i]);
+                            (progress, lerp_point(local[i], *next, t))
+                        })
+                };
+
+                let (goal_x, goal_y) = goal;
+                let dist_sq = goal_x * goal_x + goal_y * goal_y;
+                let curvature = if dist_sq == zero() {
+                    zero()
+                } else {
+                    (goal_y + goal_y) / dist_sq
+                };
+
+                (
+                    PurePursuit::Running {
+                        path,
+                        params,
+                        progress,
+                        _time: Default::default(),
+                    },
+                    TaskOutput::Running(Twist::new(
+                        params.speed,
+                        zero(),
+                        params.speed * curvature,
+                    )),
+                )
+            }
+            PurePursuit::Stopped(end) => {
+                (PurePursuit::Stopped(end), TaskOutput::Stopped(end))
+            }
+        }
+    }
+}
+
+pub fn pure_pursuit<Time, T: Float + FloatConst>(
+    path: Vec<(T, T)>,
+    params: PurePursuitParams<T>,
+) -> SF<Time, PurePursuit<Time, T>> {
+    SF::from(PurePursuit::new(path, params))
+}
+
+//
+// Ramsete
+//
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TrajectoryPoint<T> {
+    pub time: T,
+    pub pose: Pose<T>,
+    pub vel: T,
+    pub omega: T,
+}
+
+impl<T: Float + FloatConst> TrajectoryPoint<T> {
+    fn lerp(&self, other: &Self, time: T) -> Self {
+        let span = other.time - self.time;
+        let k = if span > zero() {
+            (time - self.time) / span
+        } else {
+            one()
+        };
+        let lerp = |a: T, b: T| a + (b - a) * k;
+
+        TrajectoryPoint {
+            time,
+            pose: Pose::new(
+                lerp(self.pose.x, other.pose.x),
+                lerp(self.pose.y, other.pose.y),
+                self.pose.theta
+                    + normalize_angle(other.pose.theta - self.pose.theta) * k,
+            ),
+            vel: lerp(self.vel, other.vel),
+            omega: lerp(self.omega, other.omega),
+        }
+    }
+}
+
+// Typical gains are b = 2 and zeta = 0.7 (with units of meters and seconds)
+#[derive(Copy, Clone, Debug)]
+pub struct RamseteParams<T> {
+    pub b: T,
+    pub zeta: T,
+}
+
+// Tracks a time-parameterized trajectory, stopping with the current pose once
+// the trajectory is over. The leftover is how far the step went past the end
+// of the trajectory.
+#[derive(Clone)]
+pub enum Ramsete<Time, T> {
+    Running {
+        trajectory: Vec<TrajectoryPoint<T>>,
+        params: RamseteParams<T>,
+        time: T,
+        // Index of the trajectory point at or before the current time
+        index: usize,
+        _time: PhantomData<Time>,
+    },
+    Stopped(Pose<T>, Option<Time>),
+}
+
+impl<Time, T: Float + FloatConst> Ramsete<Time, T> {
+    pub fn new(
+        trajectory: Vec<TrajectoryPoint<T>>,
+        params: RamseteParams<T>,
+    ) -> Self {
+        assert!(
+            !trajectory.is_empty(),
+            "trajectory must have at least one point"
+        );
+
+        Ramsete::Running {
+            trajectory,
+            params,
+            time: zero(),
+            index: 0,
+            _time: Default::default(),
+        }
+    }
+}
+
+impl<Time, T> SignalTrans<Time> for Ramsete<Time, T>
+where
+    Time: Into<T> + NumCast + Copy,
+    T: Float + FloatConst,
+{
+    type Input = Pose<T>;
+    type Output = TaskOutput<Twist<T>, Pose<T>>;
+
+    fn step(self, delta: Time, pose: Pose<T>) -> (Self, Self::Output) {
+        match self {
+            Ramsete::Running {
+                trajectory,
+                params,
+                time,
+                mut index,
+                ..
+            } => {
+                let time = time + delta.into();
+                let last = trajectory.len() - 1;
+                if time >= trajectory[last].time {
+                    let leftover = NumCast::from(time - trajectory[last].time);
+                    return (
+                        Ramsete::Stopped(pose, leftover),
+                        TaskOutput::Stopped(pose),
+                    );
+                }
+
+                while index < last && trajectory[index + 1].time <= time {
+                    index += 1;
+                }
+                let desired = if time < trajectory[index].time {
+                    trajectory[index]
+                } else {
+                    trajectory[index].lerp(&trajectory[index + 1], time)
+                };
+
+                let error = desired.pose.relative_to(&pose);
+                let (vel, omega) = (desired.vel, desired.omega);
+                let two = one::<T>() + one();
+                let k = two
+                    * params.zeta
+                    * (omega * omega + params.b * vel * vel).sqrt();
+                let sinc = if error.theta.abs() < T::epsilon() {
+                    one()
+                } else {
+                    error.theta.sin() / error.theta
+                };
+
+                let twist = Twist::new(
+                    vel * error.theta.cos() + k * error.x,
+                    zero(),
+                    omega + k * error.theta + params.b * vel * sinc * error.y,
+                );
+
+                (
+                    Ramsete::Running {
+                        trajectory,
+                        params,
+                        time,
+                        index,
+                        _time: Default::default(),
+                    },
+                    TaskOutput::Running(twist),
+                )
+            }
+            Ramsete::Stopped(end, _) => {
+                (Ramsete::Stopped(end, Some(delta)), TaskOutput::Stopped(end))
+            }
+        }
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        match self {
+            Ramsete::Running { .. } => None,
+            Ramsete::Stopped(_, leftover) => *leftover,
+        }
+    }
+}
+
+pub fn ramsete<Time, T>(
+    trajectory: Vec<TrajectoryPoint<T>>,
+    params: RamseteParams<T>,
+) -> SF<Time, Ramsete<Time, T>>
+where
+    Time: Into<T> + NumCast + Copy,
+    T: Float + FloatConst,
+{
+    SF::from(Ramsete::new(trajectory, params))
+}
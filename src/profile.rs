@@ -0,0 +1,326 @@
+use crate::std::marker::PhantomData;
+
+use num_traits::{one, zero, Float, NumCast};
+
+use crate::{SignalTrans, TaskOutput, SF};
+
+// Velocity change + cruise + velocity change, plus one segment to bring the
+// acceleration to 0 when retargeting an S-curve mid-move
+const MAX_SEGMENTS: usize = 8;
+
+// Bisection steps used to find the cruise velocity of a short move
+const SOLVE_ITERS: usize = 64;
+
+#[derive(Copy, Clone)]
+pub struct ProfileLimits<T> {
+    pub max_vel: T,
+    pub max_acc: T,
+    // None makes a trapezoidal profile
+    pub max_jerk: Option<T>,
+}
+
+impl<T: Float> ProfileLimits<T> {
+    // Time to change velocity by dv (>= 0), starting and ending with no
+    // acceleration
+    fn change_time(&self, dv: T) -> T {
+        match self.max_jerk {
+            None => dv / self.max_acc,
+            Some(jerk) => {
+                if dv * jerk >= self.max_acc * self.max_acc {
+                    dv / self.max_acc + self.max_acc / jerk
+                } else {
+                    let two: T = one::<T>() + one();
+                    two * (dv / jerk).sqrt()
+                }
+            }
+        }
+    }
+
+    // The acceleration profile of a velocity change is symmetric, so the
+    // distance is just the average velocity times the duration
+    fn change_dist(&self, from: T, to: T) -> T {
+        let two: T = one::<T>() + one();
+        (from + to) / two * self.change_time((to - from).abs())
+    }
+
+    fn move_dist(&self, vel: T, cruise: T) -> T {
+        self.change_dist(vel, cruise) + self.change_dist(cruise, zero())
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct ProfileSegment<T> {
+    duration: T,
+    pos: T,
+    vel: T,
+    acc: T,
+    jerk: T,
+}
+
+impl<T: Float> ProfileSegment<T> {
+    fn sample(&self, t: T) -> (T, T, T) {
+        let two: T = one::<T>() + one();
+        let six = two + two + two;
+        (
+            self.pos
+                + self.vel * t
+                + self.acc * t * t / two
+                + self.jerk * t * t * t / six,
+            self.vel + self.acc * t + self.jerk * t * t / two,
+            self.acc + self.jerk * t,
+        )
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct ProfilePlan<T> {
+    target: T,
+    segments: [ProfileSegment<T>; MAX_SEGMENTS],
+    len: usize,
+    // State at the end of the last segment
+    end: (T, T, T),
+}
+
+impl<T: Float> ProfilePlan<T> {
+    fn empty(start: (T, T, T), target: T) -> Self {
+        let seg = ProfileSegment {
+            duration: zero(),
+            pos: zero(),
+            vel: zero(),
+            acc: zero(),
+            jerk: zero(),
+        };
+
+        ProfilePlan {
+            target,
+            segments: [seg; MAX_SEGMENTS],
+            len: 0,
+            end: start,
+        }
+    }
+
+    fn new(limits: &ProfileLimits<T>, start: (T, T, T), target: T) -> Self {
+        let mut plan = Self::empty(start, target);
+
+        // Trapezoidal profiles can change acceleration instantly, but S-curves
+        // have to ramp it down first
+        let (_, _, acc) = start;
+        match limits.max_jerk {
+            Some(jerk) if acc != zero() => {
+                plan.push(acc, -acc.signum() * jerk, acc.abs() / jerk)
+            }
+            _ => plan.end.2 = zero(),
+        }
+
+        let (pos, vel, _) = plan.end;
+        let dist = target - pos;
+        let max_vel = limits.max_vel;
+
+        let (cruise_vel, cruise_time) =
+            if dist >= limits.move_dist(vel, max_vel) {
+                (max_vel, (dist - limits.move_dist(vel, max_vel)) / max_vel)
+            } else if dist <= limits.move_dist(vel, -max_vel) {
+                (-max_vel, (limits.move_dist(vel, -max_vel) - dist) / max_vel)
+            } else {
+                // Too short to reach full speed, so find the peak velocity
+                // which covers the distance exactly
+                let two: T = one::<T>() + one();
+                let mut low = -max_vel;
+                let mut high = max_vel;
+                for _ in 0..SOLVE_ITERS {
+                    let mid = (low + high) / two;
+                    if limits.move_dist(vel, mid) < dist {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+                (low, zero())
+            };
+
+        plan.push_change(limits, cruise_vel);
+        plan.push(zero(), zero(), cruise_time);
+        plan.push_change(limits, zero());
+        plan
+    }
+
+    fn push(&mut self, acc: T, jerk: T, duration: T) {
+        if duration > zero() {
+            let (pos, vel, _) = self.end;
+            let seg = ProfileSegment {
+                duration,
+                pos,
+                vel,
+                acc,
+                jerk,
+            };
+            self.segments[self.len] = seg;
+            self.len += 1;
+            self.end = seg.sample(duration);
+        }
+    }
+
+    fn push_change(&mut self, limits: &ProfileLimits<T>, to: T) {
+        let (_, vel, _) = self.end;
+        let dv = (to - vel).abs();
+        let dir = (to - vel).signum();
+        if dv == zero() {
+            return;
+        }
+
+        match limits.max_jerk {
+            None => {
+                self.push(dir * limits.max_acc, zero(), dv / limits.max_acc)
+            }
+            Some(jerk) => {
+                let peak = limits.max_acc.min((dv * jerk).sqrt());
+                let ramp_time = peak / jerk;
+                self.push(zero(), dir * jerk, ramp_time);
+                self.push(dir * peak, zero(), dv / peak - ramp_time);
+                self.push(dir * peak, -dir * jerk, ramp_time);
+            }
+        }
+    }
+
+    fn duration(&self) -> T {
+        self.segments[..self.len]
+            .iter()
+            .fold(zero(), |total, seg| total + seg.duration)
+    }
+
+    fn sample(&self, mut t: T) -> (T, T, T) {
+        for seg in &self.segments[..self.len] {
+            if t < seg.duration {
+                return seg.sample(t);
+            }
+            t = t - seg.duration;
+        }
+        (self.target, zero(), zero())
+    }
+}
+
+//
+// MotionProfile
+//
+
+// Outputs (position, velocity, acceleration) towards the target given as
+// input, stopping with the target once it is reached. Changing the target
+// re-plans from the current state, so the motion stays continuous. The
+// leftover is how far the step went past the end of the plan.
+#[derive(Copy, Clone)]
+pub enum MotionProfile<Time, T> {
+    Running {
+        limits: ProfileLimits<T>,
+        plan: ProfilePlan<T>,
+        time: T,
+        _time: PhantomData<Time>,
+    },
+    Stopped(T, Option<Time>),
+}
+
+impl<Time, T: Float> MotionProfile<Time, T> {
+    pub fn new(start: T, limits: ProfileLimits<T>) -> Self {
+        MotionProfile::Running {
+            limits,
+            plan: ProfilePlan::empty((start, zero(), zero()), start),
+            time: zero(),
+            _time: Default::default(),
+        }
+    }
+}
+
+impl<Time, T> SignalTrans<Time> for MotionProfile<Time, T>
+where
+    Time: Into<T> + NumCast + Copy,
+    T: Float,
+{
+    type Input = T;
+    type Output = TaskOutput<(T, T, T), T>;
+
+    fn step(self, delta: Time, target: T) -> (Self, Self::Output) {
+        match self {
+            MotionProfile::Running {
+                limits, plan, time, ..
+            } => {
+                let (plan, time) = if target != plan.target {
+                    (
+                        ProfilePlan::new(&limits, plan.sample(time), target),
+                        zero(),
+                    )
+                } else {
+                    (plan, time)
+                };
+
+                let time = time + delta.into();
+                if time >= plan.duration() {
+                    let leftover = NumCast::from(time - plan.duration());
+                    (
+                        MotionProfile::Stopped(plan.target, leftover),
+                        TaskOutput::Stopped(plan.target),
+                    )
+                } else {
+                    let state = plan.sample(time);
+                    (
+                        MotionProfile::Running {
+                            limits,
+                            plan,
+                            time,
+                            _time: Default::default(),
+                        },
+                        TaskOutput::Running(state),
+                    )
+                }
+            }
+            MotionProfile::Stopped(end, _) => (
+                MotionProfile::Stopped(end, Some(delta)),
+                TaskOutput::Stopped(end),
+            ),
+        }
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        match self {
+            MotionProfile::Running { .. } => None,
+            MotionProfile::Stopped(_, leftover) => *leftover,
+        }
+    }
+}
+
+pub fn trapezoidal<Time, T>(
+    start: T,
+    max_vel: T,
+    max_acc: T,
+) -> SF<Time, MotionProfile<Time, T>>
+where
+    Time: Into<T> + NumCast + Copy,
+    T: Float,
+{
+    SF::from(MotionProfile::new(
+        start,
+        ProfileLimits {
+            max_vel,
+            max_acc,
+            max_jerk: None,
+        },
+    ))
+}
+
+pub fn s_curve<Time, T>(
+    start: T,
+    max_vel: T,
+    max_acc: T,
+    max_jerk: T,
+) -> SF<Time, MotionProfile<Time, T>>
+where
+    Time: Into<T> + NumCast + Copy,
+    T: Float,
+{
+    SF::from(MotionProfile::new(
+        start,
+        ProfileLimits {
+            max_vel,
+            max_acc,
+            max_jerk: Some(max_jerk),
+        },
+    ))
+}
@@ -6,6 +6,13 @@ pub trait SignalTrans<Time>: Sized {
     // TODO Add Continuation type? Is it really usable in more than a few cases?
 
     fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output);
+
+    // How much of the last step remained after this SF finished, if it is a
+    // task which knows when it stopped. Used to hand the rest of a step on to
+    // the next task in a sequence.
+    fn leftover(&self) -> Option<Time> {
+        None
+    }
 }
 
 // Newtype wrapper so that operators can be overloaded
@@ -23,10 +30,18 @@ impl<Time, S: SignalTrans<Time>> SF<Time, S> {
         self.sf
     }
 
+    pub fn inner(&self) -> &S {
+        &self.sf
+    }
+
     pub fn step(self, delta: Time, input: S::Input) -> (Self, S::Output) {
         let (sf, output) = self.sf.step(delta, input);
         (SF::from(sf), output)
     }
+
+    pub fn leftover(&self) -> Option<Time> {
+        self.sf.leftover()
+    }
 }
 
 impl<Time, S: SignalTrans<Time>> From<S> for SF<Time, S> {
@@ -0,0 +1,375 @@
+use crate::std::{mem, rc::Rc, vec::Vec};
+
+use crate::{map, BoxedSF, Event, Occurrences, SignalTrans, SF};
+
+type Guard<Time, A, B> = BoxedSF<Time, (A, B), Event<()>>;
+
+#[derive(Clone)]
+enum StateKind<Time, A, B> {
+    Leaf(BoxedSF<Time, A, B>),
+    // Substate to enter along with this one
+    Super(Option<usize>),
+}
+
+#[derive(Clone)]
+struct StateDef<Time, A, B, K, Act> {
+    key: K,
+    parent: Option<usize>,
+    kind: StateKind<Time, A, B>,
+    entry: Vec<Act>,
+    exit: Vec<Act>,
+}
+
+#[derive(Clone)]
+struct TransitionDef<Time, A, B> {
+    source: usize,
+    target: usize,
+    guard: Guard<Time, A, B>,
+}
+
+#[derive(Clone)]
+struct Definition<Time, A, B, K, Act> {
+    states: Vec<StateDef<Time, A, B, K, Act>>,
+    transitions: Vec<TransitionDef<Time, A, B>>,
+}
+
+impl<Time, A, B, K: PartialEq, Act> Definition<Time, A, B, K, Act> {
+    fn index(&self, key: &K) -> usize {
+        self.states
+            .iter()
+            .position(|state| state.key == *key)
+            .expect("unknown state")
+    }
+}
+
+impl<Time, A, B, K, Act> Definition<Time, A, B, K, Act> {
+    // States from the outermost one down to state
+    fn path(&self, state: usize) -> Vec<usize> {
+        let mut path = Vec::new();
+        let mut current = Some(state);
+        while let Some(state) = current {
+            path.push(state);
+            current = self.states[state].parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+//
+// StatechartBuilder
+//
+
+// Builds a hierarchical state machine. Leaf states each run an SF, while
+// superstates group substates so they share transitions and entry and exit
+// actions. States are identified by keys of any type (usually an enum).
+pub struct StatechartBuilder<Time, A, B, K, Act> {
+    def: Definition<Time, A, B, K, Act>,
+}
+
+impl<Time, A, B, K, Act> Default for StatechartBuilder<Time, A, B, K, Act> {
+    fn default() -> Self {
+        Self {
+            def: Definition {
+                states: Vec::new(),
+                transitions: Vec::new(),
+            },
+        }
+    }
+}
+
+impl<Time, A, B, K, Act> StatechartBuilder<Time, A, B, K, Act>
+where
+    Time: 'static,
+    A: 'static,
+    B: 'static,
+    K: PartialEq,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(
+        mut self,
+        parent: Option<K>,
+        key: K,
+        kind: StateKind<Time, A, B>,
+    ) -> Self {
+        assert!(
+            self.def.states.iter().all(|state| state.key != key),
+            "state added twice"
+        );
+
+        let parent = parent.map(|parent| self.def.index(&parent));
+        let index = self.def.states.len();
+        if let Some(parent) = parent {
+            match &mut self.def.states[parent].kind {
+                // The first substate is the initial one by default
+                StateKind::Super(initial) => {
+                    initial.get_or_insert(index);
+                }
+                StateKind::Leaf(_) => panic!("parent must be a superstate"),
+            }
+        }
+
+        self.def.states.push(StateDef {
+            key,
+            parent,
+            kind,
+            entry: Vec::new(),
+            exit: Vec::new(),
+        });
+        self
+    }
+
+    pub fn state<S>(self, key: K, sf: SF<Time, S>) -> Self
+    where
+        S: SignalTrans<Time, Input = A, Output = B> + Clone + 'static,
+    {
+        self.add(None, key, StateKind::Leaf(BoxedSF::new(sf.into_inner())))
+    }
+
+    pub fn state_in<S>(self, parent: K, key: K, sf: SF<Time, S>) -> Self
+    where
+        S: SignalTrans<Time, Input = A, Output = B> + Clone + 'static,
+    {
+        let kind = StateKind::Leaf(BoxedSF::new(sf.into_inner()));
+        self.add(Some(parent), key, kind)
+    }
+
+    pub fn superstate(self, key: K) -> Self {
+        self.add(None, key, StateKind::Super(None))
+    }
+
+    pub fn superstate_in(self, parent: K, key: K) -> Self {
+        self.add(Some(parent), key, StateKind::Super(None))
+    }
+
+    // Sets the substate entered along with a superstate (the first one added
+    // by default)
+    pub fn initial(mut self, superstate: K, substate: K) -> Self {
+        let superstate = self.def.index(&superstate);
+        let substate = self.def.index(&substate);
+        assert!(
+            self.def.states[substate].parent == Some(superstate),
+            "initial state must be a direct substate"
+        );
+
+        match &mut self.def.states[superstate].kind {
+            StateKind::Super(initial) => *initial = Some(substate),
+            StateKind::Leaf(_) => panic!("only superstates have substates"),
+        }
+        self
+    }
+
+    pub fn on_entry(mut self, key: K, action: Act) -> Self {
+        let state = self.def.index(&key);
+        self.def.states[state].entry.push(action);
+        self
+    }
+
+    pub fn on_exit(mut self, key: K, action: Act) -> Self {
+        let state = self.def.index(&key);
+        self.def.states[state].exit.push(action);
+        self
+    }
+
+    // Goes from source (or any of its substates) to target when guard has an
+    // event (or events). The guard gets the input and the output of the active
+    // state, and restarts each time source is entered.
+    pub fn transition<G>(
+        mut self,
+        source: K,
+        target: K,
+        guard: SF<Time, G>,
+    ) -> Self
+    where
+        G: SignalTrans<Time, Input = (A, B)> + Clone + 'static,
+        G::Output: Occurrences<Time>,
+        Time: Copy,
+    {
+        let guard = guard
+            | map(|events: G::Output| {
+                Event::from(events.occurrences().next().map(|_| ()))
+            });
+        let source = self.def.index(&source);
+        let target = self.def.index(&target);
+        self.def.transitions.push(TransitionDef {
+            source,
+            target,
+            guard: BoxedSF::new(guard.into_inner()),
+        });
+        self
+    }
+
+    pub fn build(self, initial: K) -> SF<Time, Statechart<Time, A, B, K, Act>>
+    where
+        Time: Copy,
+        A: Clone,
+        B: Clone,
+        Act: Clone,
+    {
+        assert!(
+            self.def.states.iter().all(|state| match state.kind {
+                StateKind::Leaf(_) => true,
+                StateKind::Super(initial) => initial.is_some(),
+            }),
+            "superstates must have at least one substate"
+        );
+
+        let initial = self.def.index(&initial);
+        let mut machine = Statechart {
+            def: Rc::new(self.def),
+            active: Vec::new(),
+            behavior: None,
+            guards: Vec::new(),
+            actions: Vec::new(),
+        };
+        machine.enter(0, initial);
+        SF::from(machine)
+    }
+}
+
+//
+// Statechart
+//
+
+// Runs the SF of the active leaf state, outputting it along with the exit and
+// entry actions of any transition taken (and of the initial states on the
+// first step). Transitions take effect at the end of the step, and those out
+// of inner states take priority. Leaving and entering a state (including a
+// transition to itself) restarts its SF and guards.
+#[derive(Clone)]
+pub struct Statechart<Time, A, B, K, Act> {
+    def: Rc<Definition<Time, A, B, K, Act>>,
+    // From the outermost state to the leaf
+    active: Vec<usize>,
+    behavior: Option<BoxedSF<Time, A, B>>,
+    // Guards of transitions out of active states, innermost first
+    guards: Vec<(usize, Guard<Time, A, B>)>,
+    // Actions to output on the next step
+    actions: Vec<Act>,
+}
+
+impl<Time, A, B, K, Act> Statechart<Time, A, B, K, Act>
+where
+    Act: Clone,
+{
+    // The active leaf state
+    pub fn state(&self) -> &K {
+        let leaf = self.active[self.active.len() - 1];
+        &self.def.states[leaf].key
+    }
+
+    // Whether key is the active leaf state or one of its superstates
+    pub fn is_in(&self, key: &K) -> bool
+    where
+        K: PartialEq,
+    {
+        self.active
+            .iter()
+            .any(|&state| self.def.states[state].key == *key)
+    }
+
+    // Exits the active states after the first depth of them, then enters
+    // target (which must be inside the remaining ones) and its initial
+    // substates
+    fn enter(&mut self, depth: usize, target: usize) {
+        let def = self.def.clone();
+
+        for &state in self.active[depth..].iter().rev() {
+            self.actions.extend(def.states[state].exit.iter().cloned());
+        }
+        self.active.truncate(depth);
+        let kept = &self.active;
+        self.guards.retain(|&(t, _)| {
+            let source = def.transitions[t].source;
+            kept.contains(&source)
+        });
+
+        let mut entered = def.path(target).split_off(depth);
+        loop {
+            let last = entered[entered.len() - 1];
+            match &def.states[last].kind {
+                StateKind::Leaf(sf) => {
+                    self.behavior = Some(sf.clone());
+                    break;
+                }
+                StateKind::Super(initial) => {
+                    entered.push(initial.expect("superstate without substates"))
+                }
+            }
+        }
+
+        let mut guards = Vec::new();
+        for &state in entered.iter().rev() {
+            guards.extend(
+                def.transitions
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, trans)| trans.source == state)
+                    .map(|(t, trans)| (t, trans.guard.clone())),
+            );
+        }
+        guards.append(&mut self.guards);
+        self.guards = guards;
+
+        for &state in &entered {
+            self.actions.extend(def.states[state].entry.iter().cloned());
+        }
+        self.active.extend(entered);
+    }
+}
+
+impl<Time, A, B, K, Act> SignalTrans<Time> for Statechart<Time, A, B, K, Act>
+where
+    Time: Copy,
+    A: Clone,
+    B: Clone,
+    Act: Clone,
+{
+    type Input = A;
+    type Output = (B, Vec<Act>);
+
+    fn step(mut self, delta: Time, input: A) -> (Self, Self::Output) {
+        let behavior = self.behavior.take().expect("no active state");
+        let (behavior, output) = behavior.step(delta, input.clone());
+        self.behavior = Some(behavior);
+
+        let mut fired = None;
+        let guards = mem::take(&mut self.guards);
+        for (t, guard) in guards {
+            let (guard, event) =
+                guard.step(delta, (input.clone(), output.clone()));
+            if fired.is_none() && event.is_event() {
+                fired = Some(t);
+            }
+            self.guards.push((t, guard));
+        }
+
+        if let Some(t) = fired {
+            let TransitionDef { source, target, .. } = self.def.transitions[t];
+            let target_path = self.def.path(target);
+
+            // Keep the states containing both source and target, but exit
+            // and re-enter source and target themselves
+            let source_depth = self
+                .active
+                .iter()
+                .position(|&state| state == source)
+                .expect("transition from inactive state");
+            let common = self
+                .active
+                .iter()
+                .zip(&target_path)
+                .take_while(|(a, b)| a == b)
+                .count()
+                .min(source_depth)
+                .min(target_path.len() - 1);
+            self.enter(common, target);
+        }
+
+        let actions = mem::take(&mut self.actions);
+        (self, (output, actions))
+    }
+}
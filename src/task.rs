@@ -1,12 +1,57 @@
-use num_traits::{Num, Signed};
+use crate::std::{mem, ops::Sub, rc::Rc, vec::Vec};
 
-use crate::{after, map, never, Event, SignalTrans, SF};
+use num_traits::{Num, Signed, Zero};
 
+use crate::{
+    after, map, never, After, MapOutput, Never, Occurrences, SignalTrans, SF,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum TaskOutput<B, C> {
     Running(B),
     Stopped(C),
 }
 
+impl<B, C> TaskOutput<B, C> {
+    pub fn is_running(&self) -> bool {
+        matches!(self, TaskOutput::Running(_))
+    }
+
+    pub fn running(self) -> Option<B> {
+        match self {
+            TaskOutput::Running(val) => Some(val),
+            TaskOutput::Stopped(_) => None,
+        }
+    }
+
+    pub fn stopped(self) -> Option<C> {
+        match self {
+            TaskOutput::Running(_) => None,
+            TaskOutput::Stopped(end) => Some(end),
+        }
+    }
+
+    pub fn map_running<D, F>(self, f: F) -> TaskOutput<D, C>
+    where
+        F: FnOnce(B) -> D,
+    {
+        match self {
+            TaskOutput::Running(val) => TaskOutput::Running(f(val)),
+            TaskOutput::Stopped(end) => TaskOutput::Stopped(end),
+        }
+    }
+
+    pub fn map_stopped<D, F>(self, f: F) -> TaskOutput<B, D>
+    where
+        F: FnOnce(C) -> D,
+    {
+        match self {
+            TaskOutput::Running(val) => TaskOutput::Running(val),
+            TaskOutput::Stopped(end) => TaskOutput::Stopped(f(end)),
+        }
+    }
+}
+
 /*
 TODO When/if trait aliases are a thing, use this to shorten some type
 constraints. Using the supertrait hack breaks the code since associated types
@@ -16,151 +61,264 @@ trait Task<Time, Input, Output, End> =
     SignalTrans<Time, Input = Input, Output = TaskOutput<Output, End>>;
 */
 
+//
+// TaskState
+//
+
+// Wraps a task so that it is no longer stepped once it has stopped. The end
+// value is kept, along with how much of the last step was left after stopping
+// (if known). The basic tasks are built from this and a running part which
+// only has to handle running and stopping.
 #[derive(Copy, Clone)]
-pub enum BasicTask<End, S, T> {
-    Running { run: S, stop: T },
-    Stopped(End),
+pub enum TaskState<Time, S, End> {
+    Running(S),
+    Stopped(End, Option<Time>),
 }
 
-impl<Time, End, S, T> SignalTrans<Time> for BasicTask<End, S, T>
+impl<Time, S, End> TaskState<Time, S, End> {
+    pub fn new(task: S) -> Self {
+        TaskState::Running(task)
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self, TaskState::Running(_))
+    }
+
+    pub fn end(&self) -> Option<&End> {
+        match self {
+            TaskState::Running(_) => None,
+            TaskState::Stopped(end, _) => Some(end),
+        }
+    }
+
+    pub fn task(&self) -> Option<&S> {
+        match self {
+            TaskState::Running(task) => Some(task),
+            TaskState::Stopped(..) => None,
+        }
+    }
+}
+
+impl<Time, Out, End, S> SignalTrans<Time> for TaskState<Time, S, End>
 where
     Time: Copy,
-    S: SignalTrans<Time>,
-    T: SignalTrans<Time, Input = S::Input, Output = Event<End>>,
-    S::Input: Clone,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
     End: Clone,
 {
     type Input = S::Input;
-    type Output = TaskOutput<S::Output, End>;
+    type Output = TaskOutput<Out, End>;
 
     fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
         match self {
-            BasicTask::Running { run, stop } => {
-                let (stop_next, stop_val) = stop.step(delta, input.clone());
-                if let Event::Event(stop_val) = stop_val {
-                    (
-                        BasicTask::Stopped(stop_val.clone()),
-                        TaskOutput::Stopped(stop_val),
-                    )
-                } else {
-                    let (run_next, run_val) = run.step(delta, input);
-                    (
-                        BasicTask::Running {
-                            run: run_next,
-                            stop: stop_next,
-                        },
-                        TaskOutput::Running(run_val),
-                    )
+            TaskState::Running(task) => {
+                let (task_next, val) = task.step(delta, input);
+                match val {
+                    TaskOutput::Running(val) => (
+                        TaskState::Running(task_next),
+                        TaskOutput::Running(val),
+                    ),
+                    TaskOutput::Stopped(end) => (
+                        TaskState::Stopped(end.clone(), task_next.leftover()),
+                        TaskOutput::Stopped(end),
+                    ),
                 }
             }
-            BasicTask::Stopped(val) => {
-                (BasicTask::Stopped(val.clone()), TaskOutput::Stopped(val))
-            }
+            TaskState::Stopped(end, _) => (
+                TaskState::Stopped(end.clone(), Some(delta)),
+                TaskOutput::Stopped(end),
+            ),
+        }
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        match self {
+            TaskState::Running(task) => task.leftover(),
+            TaskState::Stopped(_, leftover) => *leftover,
         }
     }
 }
 
+//
+// BasicTask
+//
+
+// Runs run until stop has an event, remembering how long ago it was
+#[derive(Copy, Clone)]
+pub struct RunUntil<Time, S, T> {
+    run: S,
+    stop: T,
+    leftover: Option<Time>,
+}
+
+impl<Time, S, T> SignalTrans<Time> for RunUntil<Time, S, T>
+where
+    Time: Copy,
+    S: SignalTrans<Time>,
+    T: SignalTrans<Time, Input = S::Input>,
+    T::Output: Occurrences<Time>,
+    S::Input: Clone,
+{
+    type Input = S::Input;
+    type Output =
+        TaskOutput<S::Output, <T::Output as Occurrences<Time>>::Value>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let RunUntil { run, stop, .. } = self;
+
+        let (stop, stop_val) = stop.step(delta, input.clone());
+        if let Some((age, end)) = stop_val.occurrences().next() {
+            (
+                RunUntil {
+                    run,
+                    stop,
+                    leftover: Some(age),
+                },
+                TaskOutput::Stopped(end),
+            )
+        } else {
+            let (run, run_val) = run.step(delta, input);
+            (
+                RunUntil {
+                    run,
+                    stop,
+                    leftover: None,
+                },
+                TaskOutput::Running(run_val),
+            )
+        }
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        self.leftover
+    }
+}
+
+pub type BasicTask<Time, End, S, T> =
+    TaskState<Time, RunUntil<Time, S, T>, End>;
+
 pub fn task<Time, End, S, T>(
     run: SF<Time, S>,
     stop: SF<Time, T>,
-) -> SF<Time, BasicTask<End, S, T>>
+) -> SF<Time, BasicTask<Time, End, S, T>>
 where
     Time: Copy,
     S: SignalTrans<Time>,
-    T: SignalTrans<Time, Input = S::Input, Output = Event<End>>,
+    T: SignalTrans<Time, Input = S::Input>,
+    T::Output: Occurrences<Time, Value = End>,
     S::Input: Clone,
     End: Clone,
 {
-    SF::from(BasicTask::Running {
+    SF::from(TaskState::new(RunUntil {
         run: run.into_inner(),
         stop: stop.into_inner(),
-    })
+        leftover: None,
+    }))
 }
 
-// TODO Write separate struct to avoid some of the type constraints
-pub fn forever<Time, Out, End, S>(
+pub type Forever<Time, End, S> =
+    BasicTask<Time, End, S, Never<<S as SignalTrans<Time>>::Input, End>>;
+
+// Never stops. Use repeat_forever() to restart a task each time it stops.
+pub fn forever<Time, End, S>(
     run: SF<Time, S>,
-) -> SF<Time, impl SignalTrans<Time>>
+) -> SF<Time, Forever<Time, End, S>>
 where
-    Time: Copy,
+    Time: Copy + Zero,
     S: SignalTrans<Time>,
     S::Input: Clone,
     End: Clone,
 {
-    task(run, never::<Time, S::Input, End>())
+    task(run, never())
 }
 
-#[derive(Copy, Clone)]
-pub enum AddStop<End, S, T> {
-    Running { task: S, stop: T },
-    Stopped(End),
-}
+//
+// AddStop
+//
 
-impl<End, S, T> AddStop<End, S, T> {
-    pub fn new(task: S, stop: T) -> Self {
-        AddStop::Running {
-            task: task,
-            stop: stop,
-        }
-    }
+// Runs a task until it stops or stop has an event, whichever comes first
+#[derive(Copy, Clone)]
+pub struct StopOn<Time, S, T> {
+    task: S,
+    stop: T,
+    // Only set when stopped by an event
+    leftover: Option<Time>,
 }
 
-impl<Time, Out, End, S, T> SignalTrans<Time> for AddStop<End, S, T>
+impl<Time, Out, End, S, T> SignalTrans<Time> for StopOn<Time, S, T>
 where
     Time: Copy,
     S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
-    T: SignalTrans<Time, Input = S::Input, Output = Event<End>>,
+    T: SignalTrans<Time, Input = S::Input>,
+    T::Output: Occurrences<Time, Value = End>,
     S::Input: Clone,
-    End: Clone,
 {
     type Input = S::Input;
     type Output = TaskOutput<Out, End>;
 
     fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
-        match self {
-            AddStop::Running { task, stop } => {
-                let (stop_next, stop_val) = stop.step(delta, input.clone());
-                if let Event::Event(val) = stop_val {
-                    (AddStop::Stopped(val.clone()), TaskOutput::Stopped(val))
-                } else {
-                    let (task_next, task_val) = task.step(delta, input);
-                    match task_val {
-                        TaskOutput::Running(val) => (
-                            AddStop::new(task_next, stop_next),
-                            TaskOutput::Running(val),
-                        ),
-                        TaskOutput::Stopped(val) => (
-                            AddStop::Stopped(val.clone()),
-                            TaskOutput::Stopped(val),
-                        ),
-                    }
-                }
-            }
-            AddStop::Stopped(val) => {
-                (AddStop::Stopped(val.clone()), TaskOutput::Stopped(val))
-            }
+        let StopOn { task, stop, .. } = self;
+
+        let (stop, stop_val) = stop.step(delta, input.clone());
+        if let Some((age, end)) = stop_val.occurrences().next() {
+            (
+                StopOn {
+                    task,
+                    stop,
+                    leftover: Some(age),
+                },
+                TaskOutput::Stopped(end),
+            )
+        } else {
+            let (task, val) = task.step(delta, input);
+            (
+                StopOn {
+                    task,
+                    stop,
+                    leftover: None,
+                },
+                val,
+            )
         }
     }
+
+    fn leftover(&self) -> Option<Time> {
+        self.leftover.or_else(|| self.task.leftover())
+    }
 }
 
+pub type AddStop<Time, End, S, T> = TaskState<Time, StopOn<Time, S, T>, End>;
+
 pub fn stop_with<Time, Out, End, S, T>(
     task: SF<Time, S>,
     stop: SF<Time, T>,
-) -> SF<Time, AddStop<End, S, T>>
+) -> SF<Time, AddStop<Time, End, S, T>>
 where
     Time: Copy,
     S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
-    T: SignalTrans<Time, Input = S::Input, Output = Event<End>>,
+    T: SignalTrans<Time, Input = S::Input>,
+    T::Output: Occurrences<Time, Value = End>,
     S::Input: Clone,
     End: Clone,
 {
-    SF::from(AddStop::new(task.into_inner(), stop.into_inner()))
+    SF::from(TaskState::new(StopOn {
+        task: task.into_inner(),
+        stop: stop.into_inner(),
+        leftover: None,
+    }))
 }
 
+// Stops with None if the task hasn't stopped by the time limit
+pub type Timeout<Time, Out, End, S> = AddStop<
+    Time,
+    Option<End>,
+    MapOutput<Time, S, TaskOutput<Out, Option<End>>>,
+    After<Time, <S as SignalTrans<Time>>::Input, Option<End>>,
+>;
+
 pub fn timeout<Time, Out, End, S>(
     task: SF<Time, S>,
     time: Time,
-) -> SF<Time, impl SignalTrans<Time>>
+) -> SF<Time, Timeout<Time, Out, End, S>>
 where
     Time: Signed + Num + Copy,
     S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
@@ -176,45 +334,73 @@ where
     )
 }
 
-// TODO Make an enum storing the state to avoid calling step() on first even
-// when it has finished
+//
+// SeqTask
+//
+
+// Runs one task and then another. The first task is dropped once it stops,
+// but its end value is kept.
 #[derive(Copy, Clone)]
-pub struct SeqTask<S, T> {
-    first: S,
-    second: T,
+pub enum SeqTask<Time, FirstEnd, End, S, T> {
+    First(S, T),
+    Second(FirstEnd, TaskState<Time, T, End>),
 }
 
-impl<S, T> SeqTask<S, T> {
+impl<Time, FirstEnd, End, S, T> SeqTask<Time, FirstEnd, End, S, T> {
     pub fn new(first: S, second: T) -> Self {
-        Self {
-            first: first,
-            second: second,
+        SeqTask::First(first, second)
+    }
+
+    pub fn first_end(&self) -> Option<&FirstEnd> {
+        match self {
+            SeqTask::First(..) => None,
+            SeqTask::Second(end, _) => Some(end),
         }
     }
 }
 
-impl<Time, Out, FirstEnd, End, S, T> SignalTrans<Time> for SeqTask<S, T>
+impl<Time, Out, FirstEnd, End, S, T> SignalTrans<Time>
+    for SeqTask<Time, FirstEnd, End, S, T>
 where
     Time: Copy,
     S: SignalTrans<Time, Output = TaskOutput<Out, FirstEnd>>,
     T: SignalTrans<Time, Input = S::Input, Output = TaskOutput<Out, End>>,
     S::Input: Clone,
+    End: Clone,
 {
     type Input = S::Input;
     type Output = TaskOutput<Out, End>;
 
     fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
-        let SeqTask { first, second } = self;
+        match self {
+            SeqTask::First(first, second) => {
+                let (first_next, first_val) = first.step(delta, input.clone());
+                match first_val {
+                    TaskOutput::Running(val) => (
+                        SeqTask::First(first_next, second),
+                        TaskOutput::Running(val),
+                    ),
+                    TaskOutput::Stopped(first_end) => {
+                        // Only run the second task for the part of the step
+                        // after the first one stopped
+                        let leftover = first_next.leftover().unwrap_or(delta);
+                        let (second, val) =
+                            TaskState::new(second).step(leftover, input);
+                        (SeqTask::Second(first_end, second), val)
+                    }
+                }
+            }
+            SeqTask::Second(first_end, second) => {
+                let (second, val) = second.step(delta, input);
+                (SeqTask::Second(first_end, second), val)
+            }
+        }
+    }
 
-        let (first_next, first_val) = first.step(delta, input.clone());
-        if let TaskOutput::Running(first_val) = first_val {
-            (
-                SeqTask::new(first_next, second),
-                TaskOutput::Running(first_val),
-            )
-        } else {
-            let (second_next, second_val) = second.step(delta, input);
-            (SeqTask::new(first_next, second_next), second_val)
+    fn leftover(&self) -> Option<Time> {
+        match self {
+            SeqTask::First(..) => None,
+            SeqTask::Second(_, second) => second.leftover(),
         }
     }
 }
@@ -222,16 +408,688 @@ where
 pub fn sequence<Time, Out, FirstEnd, End, S, T>(
     first: SF<Time, S>,
     second: SF<Time, T>,
-) -> SF<Time, SeqTask<S, T>>
+) -> SF<Time, SeqTask<Time, FirstEnd, End, S, T>>
 where
     Time: Copy,
     S: SignalTrans<Time, Output = TaskOutput<Out, FirstEnd>>,
     T: SignalTrans<Time, Input = S::Input, Output = TaskOutput<Out, End>>,
     S::Input: Clone,
+    End: Clone,
 {
     SF::from(SeqTask::new(first.into_inner(), second.into_inner()))
 }
 
+//
+// SequenceVec
+//
+
+// Runs a list of tasks in order, like sequence![] but with the tasks chosen at
+// runtime
+#[derive(Clone)]
+pub struct RunSequence<Time, S, End> {
+    // In reverse order, so the current task is at the end
+    tasks: Vec<S>,
+    ends: Vec<End>,
+    leftover: Option<Time>,
+}
+
+impl<Time, S, End> RunSequence<Time, S, End> {
+    // Index of the current task
+    pub fn index(&self) -> usize {
+        self.ends.len()
+    }
+}
+
+impl<Time, Out, End, S> SignalTrans<Time> for RunSequence<Time, S, End>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
+    S::Input: Clone,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<Out, Vec<End>>;
+
+    fn step(mut self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let mut time = delta;
+        while let Some(task) = self.tasks.pop() {
+            let (task, val) = task.step(time, input.clone());
+            match val {
+                TaskOutput::Running(val) => {
+                    self.tasks.push(task);
+                    return (self, TaskOutput::Running(val));
+                }
+                TaskOutput::Stopped(end) => {
+                    // Run the next task for the rest of the step
+                    self.ends.push(end);
+                    time = task.leftover().unwrap_or(time);
+                }
+            }
+        }
+
+        self.leftover = Some(time);
+        let ends = mem::take(&mut self.ends);
+        (self, TaskOutput::Stopped(ends))
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        self.leftover
+    }
+}
+
+pub type SequenceVec<Time, S, End> =
+    TaskState<Time, RunSequence<Time, S, End>, Vec<End>>;
+
+impl<Time, S, End> SequenceVec<Time, S, End> {
+    // Index of the running task, or the number of tasks once all have stopped
+    pub fn index(&self) -> usize {
+        match self {
+            TaskState::Running(run) => run.index(),
+            TaskState::Stopped(ends, _) => ends.len(),
+        }
+    }
+}
+
+// Use boxed() to sequence different types of tasks
+pub fn sequence_vec<Time, Out, End, S>(
+    mut tasks: Vec<SF<Time, S>>,
+) -> SF<Time, SequenceVec<Time, S, End>>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
+    S::Input: Clone,
+    End: Clone,
+{
+    tasks.reverse();
+    SF::from(TaskState::new(RunSequence {
+        tasks: tasks.into_iter().map(SF::into_inner).collect(),
+        ends: Vec::new(),
+        leftover: None,
+    }))
+}
+
+//
+// AndThen
+//
+
+// Runs a task, then the task built from its end value
+#[derive(Clone)]
+pub enum AndThen<FirstEnd, S, U> {
+    First {
+        task: S,
+        next: Rc<dyn Fn(FirstEnd) -> U>,
+    },
+    Second(U),
+}
+
+impl<Time, Out, FirstEnd, End, S, U> SignalTrans<Time>
+    for AndThen<FirstEnd, S, U>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, FirstEnd>>,
+    U: SignalTrans<Time, Input = S::Input, Output = TaskOutput<Out, End>>,
+    S::Input: Clone,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<Out, End>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        match self {
+            AndThen::First { task, next } => {
+                let (task_next, task_val) = task.step(delta, input.clone());
+                match task_val {
+                    TaskOutput::Running(val) => (
+                        AndThen::First {
+                            task: task_next,
+                            next,
+                        },
+                        TaskOutput::Running(val),
+                    ),
+                    TaskOutput::Stopped(end) => {
+                        let leftover = task_next.leftover().unwrap_or(delta);
+                        let (second, val) = next(end).step(leftover, input);
+                        (AndThen::Second(second), val)
+                    }
+                }
+            }
+            AndThen::Second(task) => {
+                let (task_next, val) = task.step(delta, input);
+                (AndThen::Second(task_next), val)
+            }
+        }
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        match self {
+            AndThen::First { .. } => None,
+            AndThen::Second(task) => task.leftover(),
+        }
+    }
+}
+
+// Use boxed() in next to choose between different types of tasks
+pub fn and_then<Time, Out, FirstEnd, End, S, U, F>(
+    task: SF<Time, S>,
+    next: F,
+) -> SF<Time, AndThen<FirstEnd, S, U>>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, FirstEnd>>,
+    U: SignalTrans<Time, Input = S::Input, Output = TaskOutput<Out, End>>,
+    S::Input: Clone,
+    F: Fn(FirstEnd) -> SF<Time, U> + 'static,
+{
+    SF::from(AndThen::First {
+        task: task.into_inner(),
+        next: Rc::new(move |end| next(end).into_inner()),
+    })
+}
+
+//
+// Repeat
+//
+
+// Restarts a task from a clone of its initial state each time it stops, until
+// it has run the given number of times (or forever if None). The restarted
+// task is stepped for the rest of the step, so it may run several times in one
+// step. The output is None on steps which end with the task waiting to
+// restart.
+#[derive(Clone)]
+pub enum Repeat<Time, S, End> {
+    Running {
+        init: S,
+        task: S,
+        remaining: Option<usize>,
+    },
+    Stopped(End, Option<Time>),
+}
+
+impl<Time, Out, End, S> SignalTrans<Time> for Repeat<Time, S, End>
+where
+    Time: Copy + PartialOrd,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>> + Clone,
+    S::Input: Clone,
+    End: Clone,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<Option<Out>, End>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let (init, mut task, mut remaining) = match self {
+            Repeat::Running {
+                init,
+                task,
+                remaining,
+            } => (init, task, remaining),
+            Repeat::Stopped(end, _) => {
+                return (
+                    Repeat::Stopped(end.clone(), Some(delta)),
+                    TaskOutput::Stopped(end),
+                );
+            }
+        };
+
+        // Each iteration either finishes the step or restarts the task with
+        // less time than the last, so this always ends
+        let mut time = delta;
+        loop {
+            let (task_next, val) = task.step(time, input.clone());
+            let end = match val {
+                TaskOutput::Running(val) => {
+                    return (
+                        Repeat::Running {
+                            init,
+                            task: task_next,
+                            remaining,
+                        },
+                        TaskOutput::Running(Some(val)),
+                    );
+                }
+                TaskOutput::Stopped(end) => end,
+            };
+
+            let leftover = task_next.leftover();
+            remaining = remaining.map(|n| n - 1);
+            if remaining == Some(0) {
+                return (
+                    Repeat::Stopped(end.clone(), leftover),
+                    TaskOutput::Stopped(end),
+                );
+            }
+
+            // A task which doesn't say how much time it left, or which
+            // stopped without taking any, is restarted on the next step
+            task = init.clone();
+            match leftover {
+                Some(leftover) if leftover < time => time = leftover,
+                _ => {
+                    return (
+                        Repeat::Running {
+                            init,
+                            task,
+                            remaining,
+                        },
+                        TaskOutput::Running(None),
+                    );
+                }
+            }
+        }
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        match self {
+            Repeat::Running { task, .. } => task.leftover(),
+            Repeat::Stopped(_, leftover) => *leftover,
+        }
+    }
+}
+
+pub fn repeat<Time, Out, End, S>(
+    times: usize,
+    task: SF<Time, S>,
+) -> SF<Time, Repeat<Time, S, End>>
+where
+    Time: Copy + PartialOrd,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>> + Clone,
+    S::Input: Clone,
+    End: Clone,
+{
+    assert!(times > 0, "a task must be repeated at least once");
+
+    let task = task.into_inner();
+    SF::from(Repeat::Running {
+        init: task.clone(),
+        task,
+        remaining: Some(times),
+    })
+}
+
+pub fn repeat_forever<Time, Out, End, S>(
+    task: SF<Time, S>,
+) -> SF<Time, Repeat<Time, S, End>>
+where
+    Time: Copy + PartialOrd,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>> + Clone,
+    S::Input: Clone,
+    End: Clone,
+{
+    let task = task.into_inner();
+    SF::from(Repeat::Running {
+        init: task.clone(),
+        task,
+        remaining: None,
+    })
+}
+
+//
+// Retry
+//
+
+#[derive(Copy, Clone, Debug)]
+pub struct RetryPolicy<Time> {
+    // Including the first attempt
+    pub max_attempts: usize,
+    // Time to wait after a failed attempt before restarting the task
+    pub backoff: Option<Time>,
+}
+
+// Restarts a task from a clone of its initial state each time it stops with
+// an error, stopping with the first success or the last error. Like repeat(),
+// the restarted task is stepped for the rest of the step. The output is None on
+// steps which end with the task waiting to restart.
+#[derive(Clone)]
+pub enum Retry<Time, S, T, E> {
+    Running {
+        init: S,
+        task: S,
+        // Time left to wait before starting the task
+        waiting: Option<Time>,
+        attempts: usize,
+        policy: RetryPolicy<Time>,
+    },
+    Stopped(Result<T, E>, Option<Time>),
+}
+
+impl<Time, Out, T, E, S> SignalTrans<Time> for Retry<Time, S, T, E>
+where
+    Time: Copy + PartialOrd + Sub<Output = Time>,
+    S: SignalTrans<Time, Output = TaskOutput<Out, Result<T, E>>> + Clone,
+    S::Input: Clone,
+    T: Clone,
+    E: Clone,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<Option<Out>, Result<T, E>>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let (init, mut task, mut waiting, mut attempts, policy) = match self {
+            Retry::Running {
+                init,
+                task,
+                waiting,
+                attempts,
+                policy,
+            } => (init, task, waiting, attempts, policy),
+            Retry::Stopped(end, _) => {
+                return (
+                    Retry::Stopped(end.clone(), Some(delta)),
+                    TaskOutput::Stopped(end),
+                );
+            }
+        };
+
+        // Each iteration either finishes the step or fails an attempt with
+        // less time than the last, so this always ends
+        let mut time = delta;
+        loop {
+            if let Some(remaining) = waiting {
+                if time <= remaining {
+                    return (
+                        Retry::Running {
+                            init,
+                            task,
+                            waiting: Some(remaining - time),
+                            attempts,
+                            policy,
+                        },
+                        TaskOutput::Running(None),
+                    );
+                }
+                time = time - remaining;
+                waiting = None;
+            }
+
+            let (task_next, val) = task.step(time, input.clone());
+            let end = match val {
+                TaskOutput::Running(val) => {
+                    return (
+                        Retry::Running {
+                            init,
+                            task: task_next,
+                            waiting,
+                            attempts,
+                            policy,
+                        },
+                        TaskOutput::Running(Some(val)),
+                    );
+                }
+                TaskOutput::Stopped(end) => end,
+            };
+
+            let leftover = task_next.leftover();
+            attempts += 1;
+            if end.is_ok() || attempts >= policy.max_attempts {
+                return (
+                    Retry::Stopped(end.clone(), leftover),
+                    TaskOutput::Stopped(end),
+                );
+            }
+
+            // The wait starts from when the task failed. A task which doesn't
+            // say how much time it left, or which failed without taking any,
+            // is restarted (or starts waiting) on the next step.
+            task = init.clone();
+            waiting = policy.backoff;
+            match leftover {
+                Some(leftover) if leftover < time => time = leftover,
+                _ => {
+                    return (
+                        Retry::Running {
+                            init,
+                            task,
+                            waiting,
+                            attempts,
+                            policy,
+                        },
+                        TaskOutput::Running(None),
+                    );
+                }
+            }
+        }
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        match self {
+            Retry::Running {
+                waiting: Some(_), ..
+            } => None,
+            Retry::Running { task, .. } => task.leftover(),
+            Retry::Stopped(_, leftover) => *leftover,
+        }
+    }
+}
+
+pub fn retry<Time, Out, T, E, S>(
+    task: SF<Time, S>,
+    policy: RetryPolicy<Time>,
+) -> SF<Time, Retry<Time, S, T, E>>
+where
+    Time: Copy + PartialOrd + Sub<Output = Time>,
+    S: SignalTrans<Time, Output = TaskOutput<Out, Result<T, E>>> + Clone,
+    S::Input: Clone,
+    T: Clone,
+    E: Clone,
+{
+    assert!(
+        policy.max_attempts > 0,
+        "a task must be attempted at least once"
+    );
+
+    let task = task.into_inner();
+    SF::from(Retry::Running {
+        init: task.clone(),
+        task,
+        waiting: None,
+        attempts: 0,
+        policy,
+    })
+}
+
+//
+// Pausable
+//
+
+// Pauses a task while the last event from control was true, without stepping
+// it (so its time doesn't advance). Pause events take effect from the step
+// they occur in. The output is None while paused.
+#[derive(Clone)]
+pub struct Pausable<Time, S, C, End> {
+    task: TaskState<Time, S, End>,
+    control: C,
+    paused: bool,
+}
+
+impl<Time, S, C, End> Pausable<Time, S, C, End> {
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+impl<Time, Out, End, S, C> SignalTrans<Time> for Pausable<Time, S, C, End>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
+    C: SignalTrans<Time, Input = S::Input>,
+    C::Output: Occurrences<Time, Value = bool>,
+    S::Input: Clone,
+    End: Clone,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<Option<Out>, End>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let Pausable {
+            task,
+            control,
+            paused,
+        } = self;
+
+        let (control, event) = control.step(delta, input.clone());
+        let paused = match event.occurrences().last() {
+            Some((_, paused)) => paused,
+            None => paused,
+        };
+        if paused && task.is_running() {
+            return (
+                Pausable {
+                    task,
+                    control,
+                    paused,
+                },
+                TaskOutput::Running(None),
+            );
+        }
+
+        let (task, val) = task.step(delta, input);
+        (
+            Pausable {
+                task,
+                control,
+                paused,
+            },
+            val.map_running(Some),
+        )
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        self.task.leftover()
+    }
+}
+
+pub fn pausable<Time, Out, End, S, C>(
+    task: SF<Time, S>,
+    control: SF<Time, C>,
+) -> SF<Time, Pausable<Time, S, C, End>>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
+    C: SignalTrans<Time, Input = S::Input>,
+    C::Output: Occurrences<Time, Value = bool>,
+    S::Input: Clone,
+    End: Clone,
+{
+    SF::from(Pausable {
+        task: TaskState::new(task.into_inner()),
+        control: control.into_inner(),
+        paused: false,
+    })
+}
+
+//
+// Interrupt
+//
+
+// Each time trigger has an event, pauses a task and runs the task built from
+// the event value in its place, resuming the paused task for the rest of the
+// step once the interrupting one stops. Events are ignored while interrupted,
+// as are any after the first in a step.
+#[derive(Clone)]
+pub struct Interrupt<Time, S, C, I, U, End> {
+    task: TaskState<Time, S, End>,
+    trigger: C,
+    handler: Rc<dyn Fn(I) -> U>,
+    interrupt: Option<U>,
+}
+
+impl<Time, S, C, I, U, End> Interrupt<Time, S, C, I, U, End> {
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupt.is_some()
+    }
+}
+
+impl<Time, Out, End, IntEnd, I, S, C, U> SignalTrans<Time>
+    for Interrupt<Time, S, C, I, U, End>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
+    C: SignalTrans<Time, Input = S::Input>,
+    C::Output: Occurrences<Time, Value = I>,
+    U: SignalTrans<Time, Input = S::Input, Output = TaskOutput<Out, IntEnd>>,
+    S::Input: Clone,
+    End: Clone,
+{
+    type Input = S::Input;
+    type Output = TaskOutput<Out, End>;
+
+    fn step(self, delta: Time, input: Self::Input) -> (Self, Self::Output) {
+        let Interrupt {
+            task,
+            trigger,
+            handler,
+            interrupt,
+        } = self;
+
+        let (trigger, events) = trigger.step(delta, input.clone());
+        let interrupt = match (interrupt, events.occurrences().next()) {
+            (Some(interrupt), _) => Some(interrupt),
+            (None, Some((_, val))) if task.is_running() => Some(handler(val)),
+            (None, _) => None,
+        };
+
+        // Only resume the task for the part of the step after the interrupt
+        let time = match interrupt {
+            Some(interrupt) => {
+                let (interrupt, val) = interrupt.step(delta, input.clone());
+                match val {
+                    TaskOutput::Running(val) => {
+                        return (
+                            Interrupt {
+                                task,
+                                trigger,
+                                handler,
+                                interrupt: Some(interrupt),
+                            },
+                            TaskOutput::Running(val),
+                        );
+                    }
+                    TaskOutput::Stopped(_) => {
+                        interrupt.leftover().unwrap_or(delta)
+                    }
+                }
+            }
+            None => delta,
+        };
+
+        let (task, val) = task.step(time, input);
+        (
+            Interrupt {
+                task,
+                trigger,
+                handler,
+                interrupt: None,
+            },
+            val,
+        )
+    }
+
+    fn leftover(&self) -> Option<Time> {
+        self.task.leftover()
+    }
+}
+
+// Use boxed() in handler to choose between different types of tasks
+pub fn interrupt<Time, Out, End, IntEnd, I, S, C, U, F>(
+    task: SF<Time, S>,
+    trigger: SF<Time, C>,
+    handler: F,
+) -> SF<Time, Interrupt<Time, S, C, I, U, End>>
+where
+    Time: Copy,
+    S: SignalTrans<Time, Output = TaskOutput<Out, End>>,
+    C: SignalTrans<Time, Input = S::Input>,
+    C::Output: Occurrences<Time, Value = I>,
+    U: SignalTrans<Time, Input = S::Input, Output = TaskOutput<Out, IntEnd>>,
+    S::Input: Clone,
+    End: Clone,
+    F: Fn(I) -> SF<Time, U> + 'static,
+{
+    SF::from(Interrupt {
+        task: TaskState::new(task.into_inner()),
+        trigger: trigger.into_inner(),
+        handler: Rc::new(move |val| handler(val).into_inner()),
+        interrupt: None,
+    })
+}
+
 // Makes a binary tree out of the arguments to avoid high nesting of SeqTask
 // structs towards the end (or beginning) of the sequence
 #[macro_export]
@@ -0,0 +1,442 @@
+// Checks that SFs behave the way the combinators assume they do, by running
+// them over input traces. Mainly for validating custom SignalTrans impls, e.g.
+//
+//     let trace = random_trace(&mut Rng::new(1), 100, |r| r.range(0.0, 0.1),
+//         |r| r.range(-1.0, 1.0));
+//     check_left_identity(my_sf(), &trace).unwrap();
+//
+// Each check returns the first step at which the two sides differ.
+
+use crate::std::vec::Vec;
+
+use crate::{compose, fanout, identity, split, SignalTrans, SF};
+
+//
+// Traces
+//
+
+// Small deterministic generator (xorshift64*) so that traces can be
+// reproduced from a seed without any extra dependencies
+#[derive(Copy, Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // The state must never be 0
+        Rng {
+            state: (seed ^ 0x9e37_79b9_7f4a_7c15) | 1,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Uniform in [low, high)
+    pub fn range(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.next_f64()
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u64() >> 63 == 1
+    }
+}
+
+// Generates len steps of (delta, input)
+pub fn random_trace<Time, A, D, I>(
+    rng: &mut Rng,
+    len: usize,
+    mut delta: D,
+    mut input: I,
+) -> Vec<(Time, A)>
+where
+    D: FnMut(&mut Rng) -> Time,
+    I: FnMut(&mut Rng) -> A,
+{
+    (0..len)
+        .map(|_| {
+            let delta = delta(rng);
+            (delta, input(rng))
+        })
+        .collect()
+}
+
+// Steps sf through the trace, collecting its outputs
+pub fn run<Time, S>(
+    sf: SF<Time, S>,
+    trace: &[(Time, S::Input)],
+) -> Vec<S::Output>
+where
+    Time: Copy,
+    S: SignalTrans<Time>,
+    S::Input: Clone,
+{
+    let mut sf = sf;
+    let mut outputs = Vec::with_capacity(trace.len());
+    for (delta, input) in trace {
+        let (next, output) = sf.step(*delta, input.clone());
+        sf = next;
+        outputs.push(output);
+    }
+    outputs
+}
+
+//
+// Checks
+//
+
+// First step at which two runs differed, with both outputs
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mismatch<B> {
+    pub step: usize,
+    pub left: B,
+    pub right: B,
+}
+
+fn compare<B: PartialEq>(
+    left: Vec<B>,
+    right: Vec<B>,
+    offset: usize,
+) -> Result<(), Mismatch<B>> {
+    match left
+        .into_iter()
+        .zip(right)
+        .enumerate()
+        .find(|(_, (left, right))| left != right)
+    {
+        Some((step, (left, right))) => Err(Mismatch {
+            step: offset + step,
+            left,
+            right,
+        }),
+        None => Ok(()),
+    }
+}
+
+// Whether two SFs give the same outputs over the trace
+pub fn check_equal<Time, S, T>(
+    left: SF<Time, S>,
+    right: SF<Time, T>,
+    trace: &[(Time, S::Input)],
+) -> Result<(), Mismatch<S::Output>>
+where
+    Time: Copy,
+    S: SignalTrans<Time>,
+    T: SignalTrans<Time, Input = S::Input, Output = S::Output>,
+    S::Input: Clone,
+    S::Output: PartialEq,
+{
+    compare(run(left, trace), run(right, trace), 0)
+}
+
+// identity | f == f
+pub fn check_left_identity<Time, S>(
+    f: SF<Time, S>,
+    trace: &[(Time, S::Input)],
+) -> Result<(), Mismatch<S::Output>>
+where
+    Time: Copy,
+    S: SignalTrans<Time> + Clone,
+    S::Input: Clone,
+    S::Output: PartialEq,
+{
+    check_equal(compose(identity(), f.clone()), f, trace)
+}
+
+// f | identity == f
+pub fn check_right_identity<Time, S>(
+    f: SF<Time, S>,
+    trace: &[(Time, S::Input)],
+) -> Result<(), Mismatch<S::Output>>
+where
+    Time: Copy,
+    S: SignalTrans<Time> + Clone,
+    S::Input: Clone,
+    S::Output: PartialEq,
+{
+    check_equal(compose(f.clone(), identity()), f, trace)
+}
+
+// (f | g) | h == f | (g | h)
+pub fn check_associativity<Time, S, T, U>(
+    f: SF<Time, S>,
+    g: SF<Time, T>,
+    h: SF<Time, U>,
+    trace: &[(Time, S::Input)],
+) -> Result<(), Mismatch<U::Output>>
+where
+    Time: Copy,
+    S: SignalTrans<Time> + Clone,
+    T: SignalTrans<Time, Input = S::Output> + Clone,
+    U: SignalTrans<Time, Input = T::Output> + Clone,
+    S::Input: Clone,
+    U::Output: PartialEq,
+{
+    let left = compose(compose(f.clone(), g.clone()), h.clone());
+    let right = compose(f, compose(g, h));
+    check_equal(left, right, trace)
+}
+
+// f ^ g gives the same outputs as running f and g separately on each half of
+// the input
+#[allow(clippy::type_complexity)]
+pub fn check_split<Time, S, T>(
+    f: SF<Time, S>,
+    g: SF<Time, T>,
+    trace: &[(Time, (S::Input, T::Input))],
+) -> Result<(), Mismatch<(S::Output, T::Output)>>
+where
+    Time: Copy,
+    S: SignalTrans<Time> + Clone,
+    T: SignalTrans<Time> + Clone,
+    S::Input: Clone,
+    T::Input: Clone,
+    S::Output: PartialEq,
+    T::Output: PartialEq,
+{
+    let firsts: Vec<_> = trace
+        .iter()
+        .map(|(delta, (a, _))| (*delta, a.clone()))
+        .collect();
+    let seconds: Vec<_> = trace
+        .iter()
+        .map(|(delta, (_, b))| (*delta, b.clone()))
+        .collect();
+    let separate = run(f.clone(), &firsts)
+        .into_iter()
+        .zip(run(g.clone(), &seconds))
+        .collect();
+
+    compare(run(split(f, g), trace), separate, 0)
+}
+
+// f & g gives the same outputs as running f and g separately on the input
+#[allow(clippy::type_complexity)]
+pub fn check_fanout<Time, S, T>(
+    f: SF<Time, S>,
+    g: SF<Time, T>,
+    trace: &[(Time, S::Input)],
+) -> Result<(), Mismatch<(S::Output, T::Output)>>
+where
+    Time: Copy,
+    S: SignalTrans<Time> + Clone,
+    T: SignalTrans<Time, Input = S::Input> + Clone,
+    S::Input: Clone,
+    S::Output: PartialEq,
+    T::Output: PartialEq,
+{
+    let separate = run(f.clone(), trace)
+        .into_iter()
+        .zip(run(g.clone(), trace))
+        .collect();
+
+    compare(run(fanout(f, g), trace), separate, 0)
+}
+
+// Running clones of f over the same trace gives the same outputs
+pub fn check_deterministic<Time, S>(
+    f: SF<Time, S>,
+    trace: &[(Time, S::Input)],
+) -> Result<(), Mismatch<S::Output>>
+where
+    Time: Copy,
+    S: SignalTrans<Time> + Clone,
+    S::Input: Clone,
+    S::Output: PartialEq,
+{
+    check_equal(f.clone(), f, trace)
+}
+
+// A clone taken at any step doesn't share state with the original: after the
+// original runs through the rest of the trace, the clone still gives the same
+// outputs over it. Catches state shared through Rc<Cell> and the like.
+pub fn check_clone_independence<Time, S>(
+    f: SF<Time, S>,
+    trace: &[(Time, S::Input)],
+) -> Result<(), Mismatch<S::Output>>
+where
+    Time: Copy,
+    S: SignalTrans<Time> + Clone,
+    S::Input: Clone,
+    S::Output: PartialEq,
+{
+    let mut sf = f;
+    for (i, (delta, input)) in trace.iter().enumerate() {
+        let rest = &trace[i..];
+        let clone = sf.clone();
+        let original = run(sf.clone(), rest);
+        compare(original, run(clone, rest), i)?;
+
+        sf = sf.step(*delta, input.clone()).0;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::std::{cell::Cell, rc::Rc};
+
+    use crate::{
+        after, at, delay, derivative, every, hold, integral, map, repeatedly,
+        Event, Lift,
+    };
+
+    fn trace(seed: u64) -> Vec<(f64, f64)> {
+        random_trace(
+            &mut Rng::new(seed),
+            100,
+            |r| r.range(0.01, 0.1),
+            |r| r.range(-1.0, 1.0),
+        )
+    }
+
+    fn event_trace(seed: u64) -> Vec<(f64, Event<f64>)> {
+        random_trace(
+            &mut Rng::new(seed),
+            100,
+            |r| r.range(0.01, 0.1),
+            |r| {
+                if r.next_bool() {
+                    Event::new(r.range(-1.0, 1.0))
+                } else {
+                    Event::NoEvent
+                }
+            },
+        )
+    }
+
+    // Only the last two inputs are positive
+    fn positive_trace() -> Vec<(f64, f64)> {
+        vec![(0.1, 0.0), (0.1, 0.0), (0.1, 1.0), (0.1, 1.0)]
+    }
+
+    // Numbers the positive inputs, with a counter shared between clones
+    fn shared_counter() -> SF<f64, Lift<f64, f64, usize>> {
+        let count = Rc::new(Cell::new(0));
+        map(move |x: f64| {
+            if x > 0.0 {
+                count.set(count.get() + 1);
+                count.get()
+            } else {
+                0
+            }
+        })
+    }
+
+    // Outputs a random number for each positive input, from a generator shared
+    // between clones
+    fn shared_random() -> SF<f64, Lift<f64, f64, f64>> {
+        let rng = Rc::new(Cell::new(Rng::new(8)));
+        map(move |x: f64| {
+            if x > 0.0 {
+                let mut next = rng.get();
+                let value = next.next_f64();
+                rng.set(next);
+                value
+            } else {
+                0.0
+            }
+        })
+    }
+
+    #[test]
+    fn identity_laws() {
+        let trace = trace(1);
+        let events = event_trace(2);
+
+        check_left_identity(identity(), &trace).unwrap();
+        check_left_identity(map(|x: f64| x * 2.0), &trace).unwrap();
+        check_left_identity(integral(), &trace).unwrap();
+        check_left_identity(derivative(), &trace).unwrap();
+        check_left_identity(delay(0.0), &trace).unwrap();
+        check_left_identity(hold(0.0), &events).unwrap();
+        check_left_identity(after(1.0, ()), &trace).unwrap();
+        check_left_identity(at(1.0, ()), &trace).unwrap();
+        check_left_identity(repeatedly(0.3, ()), &trace).unwrap();
+
+        check_right_identity(integral(), &trace).unwrap();
+        check_right_identity(hold(0.0), &events).unwrap();
+        check_right_identity(every(0.3, ()), &trace).unwrap();
+    }
+
+    #[test]
+    fn associativity() {
+        let trace = trace(3);
+
+        check_associativity(
+            map(|x: f64| x + 1.0),
+            integral(),
+            derivative(),
+            &trace,
+        )
+        .unwrap();
+        check_associativity(
+            delay(0.0),
+            map(|x: f64| Event::new(x)),
+            hold(0.0),
+            &trace,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn split_and_fanout() {
+        let trace = trace(4);
+        let events = event_trace(5);
+        let pairs: Vec<_> = trace
+            .iter()
+            .zip(&events)
+            .map(|((delta, x), (_, event))| (*delta, (*x, *event)))
+            .collect();
+
+        check_split(integral(), hold(0.0), &pairs).unwrap();
+        check_split(derivative(), identity(), &pairs).unwrap();
+
+        check_fanout(integral(), derivative(), &trace).unwrap();
+        check_fanout(after(1.0, ()), repeatedly(0.3, ()), &trace).unwrap();
+    }
+
+    #[test]
+    fn clone_independence() {
+        let trace = trace(6);
+        let events = event_trace(7);
+
+        check_clone_independence(integral(), &trace).unwrap();
+        check_clone_independence(derivative(), &trace).unwrap();
+        check_clone_independence(delay(0.0), &trace).unwrap();
+        check_clone_independence(hold(0.0), &events).unwrap();
+        check_clone_independence(after(1.0, ()), &trace).unwrap();
+        check_clone_independence(at(1.0, ()), &trace).unwrap();
+        check_clone_independence(every(0.3, ()), &trace).unwrap();
+    }
+
+    #[test]
+    fn shared_state_is_caught() {
+        let trace = positive_trace();
+
+        assert_eq!(
+            check_clone_independence(shared_counter(), &trace),
+            Err(Mismatch {
+                step: 2,
+                left: 1,
+                right: 3,
+            }),
+        );
+        assert!(matches!(
+            check_deterministic(shared_random(), &trace),
+            Err(Mismatch { step: 2, .. }),
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::std::array;
+
+use num_traits::{one, zero, Num};
+
+// Values which can be added, subtracted and scaled, so that they can be
+// integrated, differentiated and filtered. Time deltas are converted into
+// Scalar to scale by them.
+pub trait VectorSpace: Clone {
+    type Scalar: Num + Copy;
+
+    fn zero_vector() -> Self;
+    fn add_vector(self, other: Self) -> Self;
+    fn sub_vector(self, other: Self) -> Self;
+    fn scale(self, k: Self::Scalar) -> Self;
+
+    // Scales by the reciprocal of k by default, which isn't exact for
+    // integers
+    fn div_scalar(self, k: Self::Scalar) -> Self {
+        self.scale(one::<Self::Scalar>() / k)
+    }
+}
+
+//
+// Scalars
+//
+
+macro_rules! scalar_vector_space {
+    ($($scalar:ty),+) => {
+        $(
+            impl VectorSpace for $scalar {
+                type Scalar = $scalar;
+
+                fn zero_vector() -> Self {
+                    zero()
+                }
+
+                fn add_vector(self, other: Self) -> Self {
+                    self + other
+                }
+
+                fn sub_vector(self, other: Self) -> Self {
+                    self - other
+                }
+
+                fn scale(self, k: Self) -> Self {
+                    self * k
+                }
+
+                fn div_scalar(self, k: Self) -> Self {
+                    self / k
+                }
+            }
+        )+
+    }
+}
+
+scalar_vector_space!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64
+);
+
+//
+// Arrays and tuples
+//
+
+impl<V: VectorSpace, const N: usize> VectorSpace for [V; N] {
+    type Scalar = V::Scalar;
+
+    fn zero_vector() -> Self {
+        array::from_fn(|_| V::zero_vector())
+    }
+
+    fn add_vector(self, other: Self) -> Self {
+        // Arrays only iterate by value from edition 2021
+        let mut other = IntoIterator::into_iter(other);
+        self.map(|x| x.add_vector(other.next().unwrap()))
+    }
+
+    fn sub_vector(self, other: Self) -> Self {
+        let mut other = IntoIterator::into_iter(other);
+        self.map(|x| x.sub_vector(other.next().unwrap()))
+    }
+
+    fn scale(self, k: V::Scalar) -> Self {
+        self.map(|x| x.scale(k))
+    }
+
+    fn div_scalar(self, k: V::Scalar) -> Self {
+        self.map(|x| x.div_scalar(k))
+    }
+}
+
+// Elements can be different types as long as they share a scalar
+macro_rules! tuple_vector_space {
+    ($first:ident $first_idx:tt $(, $rest:ident $idx:tt)*) => {
+        impl<$first, $($rest),*> VectorSpace for ($first, $($rest),*)
+        where
+            $first: VectorSpace,
+            $($rest: VectorSpace<Scalar = $first::Scalar>),*
+        {
+            type Scalar = $first::Scalar;
+
+            fn zero_vector() -> Self {
+                ($first::zero_vector(), $($rest::zero_vector()),*)
+            }
+
+            fn add_vector(self, other: Self) -> Self {
+                (
+                    self.$first_idx.add_vector(other.$first_idx),
+                    $(self.$idx.add_vector(other.$idx)),*
+                )
+            }
+
+            fn sub_vector(self, other: Self) -> Self {
+                (
+                    self.$first_idx.sub_vector(other.$first_idx),
+                    $(self.$idx.sub_vector(other.$idx)),*
+                )
+            }
+
+            fn scale(self, k: Self::Scalar) -> Self {
+                (self.$first_idx.scale(k), $(self.$idx.scale(k)),*)
+            }
+
+            fn div_scalar(self, k: Self::Scalar) -> Self {
+                (self.$first_idx.div_scalar(k), $(self.$idx.div_scalar(k)),*)
+            }
+        }
+    };
+}
+
+tuple_vector_space!(A 0, B 1);
+tuple_vector_space!(A 0, B 1, C 2);
+tuple_vector_space!(A 0, B 1, C 2, D 3);
+
+//
+// Linear algebra types
+//
+
+#[cfg(feature = "nalgebra")]
+impl<T, const R: usize, const C: usize> VectorSpace
+    for nalgebra::SMatrix<T, R, C>
+where
+    T: nalgebra::Scalar + Num + Copy,
+{
+    type Scalar = T;
+
+    fn zero_vector() -> Self {
+        Self::from_element(zero())
+    }
+
+    fn add_vector(self, other: Self) -> Self {
+        self.zip_map(&other, |x, y| x + y)
+    }
+
+    fn sub_vector(self, other: Self) -> Self {
+        self.zip_map(&other, |x, y| x - y)
+    }
+
+    fn scale(self, k: T) -> Self {
+        self.map(|x| x * k)
+    }
+
+    fn div_scalar(self, k: T) -> Self {
+        self.map(|x| x / k)
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+impl<T, const D: usize> VectorSpace for nalgebra::Point<T, D>
+where
+    T: nalgebra::Scalar + Num + Copy,
+{
+    type Scalar = T;
+
+    fn zero_vector() -> Self {
+        Self::origin()
+    }
+
+    fn add_vector(self, other: Self) -> Self {
+        Self::from(self.coords.add_vector(other.coords))
+    }
+
+    fn sub_vector(self, other: Self) -> Self {
+        Self::from(self.coords.sub_vector(other.coords))
+    }
+
+    fn scale(self, k: T) -> Self {
+        Self::from(self.coords.scale(k))
+    }
+
+    fn div_scalar(self, k: T) -> Self {
+        Self::from(self.coords.div_scalar(k))
+    }
+}